@@ -1,21 +1,26 @@
 from __future__ import annotations
 
+import argparse
 import asyncio
 import os
+import signal
 import socket
 import sys
+import threading
+import time
 
 import uvicorn
 
 
-def find_available_port(start_port: int, max_attempts: int = 10) -> int:
+def find_available_port(host: str, start_port: int, max_attempts: int = 10) -> int:
     """Find an available port starting from start_port."""
+    family = socket.AF_INET6 if ":" in host else socket.AF_INET
     for offset in range(max_attempts):
         port = start_port + offset
         try:
             # Try to bind to the port to check availability
-            with socket.socket(socket.AF_INET, socket.SOCK_STREAM) as s:
-                s.bind(("127.0.0.1", port))
+            with socket.socket(family, socket.SOCK_STREAM) as s:
+                s.bind((host, port))
                 return port
         except OSError:
             # Port is in use, try next one
@@ -23,29 +28,124 @@ def find_available_port(start_port: int, max_attempts: int = 10) -> int:
     raise RuntimeError(f"Could not find available port in range {start_port}-{start_port + max_attempts - 1}")
 
 
-async def _serve() -> None:
-    preferred_port = int(os.getenv("RETENTION_PORT", "27888"))
+# uvicorn spells warn as "warning"
+_UVICORN_LOG_LEVELS = {"trace": "trace", "debug": "debug", "info": "info", "warn": "warning", "error": "error"}
 
-    # Find an available port, starting with the preferred one
-    try:
-        port = find_available_port(preferred_port)
-        if port != preferred_port:
-            print(f"Port {preferred_port} is in use, using port {port} instead", file=sys.stderr, flush=True)
 
-        # Write the actual port being used to stdout so Rust can read it
-        print(f"SIDECAR_PORT={port}", flush=True)
+def parse_args() -> argparse.Namespace:
+    parser = argparse.ArgumentParser(prog="python_sidecar")
+    parser.add_argument("--host", default="127.0.0.1", help="Interface to bind, e.g. ::1 for IPv6")
+    parser.add_argument("--port", type=int, help="Port chosen by the desktop app; skips the port search")
+    parser.add_argument(
+        "--log-level",
+        choices=["trace", "debug", "info", "warn", "error"],
+        default="info",
+        help="Server log verbosity",
+    )
+    parser.add_argument(
+        "--device",
+        choices=["auto", "cpu", "cuda"],
+        default="auto",
+        help="Where to run the embedding model; auto uses a GPU when one is usable",
+    )
+    parser.add_argument("--read-only", action="store_true", help="Refuse requests that would change data (demo mode)")
+    # The desktop app forwards `--sidecar-arg KEY=VALUE` as `--KEY VALUE`; an
+    # option this version doesn't know shouldn't keep the server from starting
+    args, unknown = parser.parse_known_args()
+    if unknown:
+        print(f"Ignoring unknown arguments: {' '.join(unknown)}", file=sys.stderr, flush=True)
+    return args
+
+
+async def _serve(args: argparse.Namespace) -> None:
+    if args.port is not None:
+        port = args.port
+    else:
+        preferred_port = int(os.getenv("RETENTION_PORT", "27888"))
+
+        # Find an available port, starting with the preferred one
+        try:
+            port = find_available_port(args.host, preferred_port)
+            if port != preferred_port:
+                print(f"Port {preferred_port} is in use, using port {port} instead", file=sys.stderr, flush=True)
+        except RuntimeError as e:
+            print(f"ERROR: {e}", file=sys.stderr, flush=True)
+            sys.exit(1)
 
-    except RuntimeError as e:
-        print(f"ERROR: {e}", file=sys.stderr, flush=True)
-        sys.exit(1)
+    # Write the actual address being used to stdout so Rust can read it
+    if args.host == "127.0.0.1":
+        print(f"SIDECAR_PORT={port}", flush=True)
+    else:
+        host = f"[{args.host}]" if ":" in args.host else args.host
+        print(f"SIDECAR_ADDR={host}:{port}", flush=True)
 
-    config = uvicorn.Config("python_sidecar.app:app", host="127.0.0.1", port=port, log_level="info")
+    config = uvicorn.Config("python_sidecar.app:app", host=args.host, port=port, log_level=_UVICORN_LOG_LEVELS[args.log_level])
     server = uvicorn.Server(config)
-    await server.serve()
+    # Lets POST /shutdown stop the server the same way Ctrl+C would
+    from python_sidecar.app import app
+
+    app.state.server = server
+    app.state.read_only = args.read_only
+    print(f"SIDECAR_PID={os.getpid()}", flush=True)
+    # Before READY, so the desktop app knows whether a requested GPU is in use
+    from python_sidecar.scoring import select_device
+
+    print(f"SIDECAR_DEVICE={select_device(args.device)}", flush=True)
+    await asyncio.gather(server.serve(), _announce_ready(server))
+
+
+async def _announce_ready(server: uvicorn.Server) -> None:
+    # The port is announced before binding; this tells Rust requests will
+    # actually be served now. Scoring needs the model, so wait for it to
+    # load too; SIDECAR_LOADING lines report progress in the meantime.
+    while not server.started:
+        if server.should_exit:
+            return
+        await asyncio.sleep(0.05)
+    from python_sidecar.scoring import MODEL_NAME, warm_model_cache
+
+    try:
+        await warm_model_cache()
+    except Exception as e:
+        # Still serving; /health reports the model as failed
+        print(f"Model failed to load: {e}", file=sys.stderr, flush=True)
+
+    # A model picked in the desktop app is named after its folder
+    selected = os.getenv("FLASH_AI_MODEL_DIR")
+    model = os.path.basename(os.path.normpath(selected)) if selected else MODEL_NAME
+    print(f"SIDECAR_MODEL={model}", flush=True)
+    print("SIDECAR_READY=1", flush=True)
+
+
+def _tie_to_parent() -> None:
+    """Don't outlive the desktop app, even if it's force-killed (Unix only).
+
+    Puts this process in its own group, so the app can kill everything we
+    start with one signal, and kills the group once the parent is gone.
+    On Windows the app uses a Job Object instead.
+    """
+    parent = os.getenv("FLASH_AI_PARENT_PID")
+    if os.name != "posix" or not parent:
+        return
+    os.setpgrp()
+
+    def watch() -> None:
+        while True:
+            time.sleep(1)
+            try:
+                os.kill(int(parent), 0)
+            except ProcessLookupError:
+                os.killpg(0, signal.SIGKILL)
+            except PermissionError:
+                # Still there, just not ours to signal
+                pass
+
+    threading.Thread(target=watch, name="parent-watch", daemon=True).start()
 
 
 def main() -> None:
-    asyncio.run(_serve())
+    _tie_to_parent()
+    asyncio.run(_serve(parse_args()))
 
 
 if __name__ == "__main__":
@@ -1,5 +1,6 @@
 from __future__ import annotations
 
+import hmac
 import os
 import time
 from collections import defaultdict
@@ -7,6 +8,7 @@ from typing import Dict, List, Tuple
 
 from fastapi import FastAPI, HTTPException, Query, Request, Response, status
 from fastapi.middleware.cors import CORSMiddleware
+from fastapi.responses import JSONResponse
 
 from .db import Database
 from .models import AttemptRecord, BulkCardOperation, DeckIn, DeckRecord, DeckUpdate, HealthStatus, ScoreRequest
@@ -14,6 +16,57 @@ from .scoring import get_model_cache_status, score_answer, warm_model_cache
 
 app = FastAPI(title="Retention Backend", version="0.1.0")
 
+# Demo mode: started with --read-only, anything that could change data is
+# refused. This is the enforcement; the desktop app only tells its UI to
+# hide editing controls. Registered before the auth check, so the auth
+# check runs first. The POSTs listed here don't change data: /score still
+# scores, it just doesn't save the attempt.
+_READ_ONLY_ALLOWED_PATHS = {"/shutdown", "/warm-model", "/score"}
+
+
+@app.middleware("http")
+async def enforce_read_only(request: Request, call_next):
+    if (
+        getattr(request.app.state, "read_only", False)
+        and request.method not in ("GET", "HEAD", "OPTIONS")
+        and request.url.path not in _READ_ONLY_ALLOWED_PATHS
+    ):
+        return JSONResponse(status_code=status.HTTP_403_FORBIDDEN, content={"detail": "The backend is read-only"})
+    return await call_next(request)
+
+
+# Security: The desktop app passes a per-launch token so other local processes
+# can't use this port. Registered before CORS so rejections still carry CORS
+# headers the webview can read.
+AUTH_TOKEN = os.getenv("FLASH_AI_AUTH_TOKEN")
+
+
+@app.middleware("http")
+async def require_auth_token(request: Request, call_next):
+    if AUTH_TOKEN and request.method != "OPTIONS":
+        expected = f"Bearer {AUTH_TOKEN}"
+        provided = request.headers.get("Authorization", "")
+        if not hmac.compare_digest(provided.encode(), expected.encode()):
+            return Response(status_code=status.HTTP_401_UNAUTHORIZED)
+    return await call_next(request)
+
+
+# Requests that may write data and haven't finished yet. The desktop app
+# polls GET /busy before installing an update so it doesn't kill us mid-write.
+_in_flight = 0
+
+
+@app.middleware("http")
+async def track_in_flight(request: Request, call_next):
+    global _in_flight
+    if request.method in ("GET", "HEAD", "OPTIONS"):
+        return await call_next(request)
+    _in_flight += 1
+    try:
+        return await call_next(request)
+    finally:
+        _in_flight -= 1
+
 # Configure CORS - Security: Restrict to only necessary methods and headers
 allowed_origins = os.getenv("ALLOWED_ORIGINS", "http://localhost:1420,http://127.0.0.1:1420,https://tauri.localhost").split(",")
 app.add_middleware(
@@ -110,6 +163,27 @@ async def health() -> HealthStatus:
     )
 
 
+@app.get("/version")
+async def version() -> dict:
+    return {"version": app.version}
+
+
+@app.get("/busy")
+async def busy() -> dict:
+    return {"busy": _in_flight > 0, "in_flight": _in_flight}
+
+
+@app.post("/shutdown", status_code=status.HTTP_202_ACCEPTED)
+async def request_shutdown(request: Request) -> dict:
+    # The desktop app asks for this before killing the process, so the
+    # shutdown handlers get to close the database cleanly
+    server = getattr(request.app.state, "server", None)
+    if server is None:
+        raise HTTPException(status_code=status.HTTP_503_SERVICE_UNAVAILABLE, detail="Not running under the sidecar entry point")
+    server.should_exit = True
+    return {"status": "shutting down"}
+
+
 @app.post("/score", response_model=AttemptRecord)
 async def score(payload: ScoreRequest, request: Request) -> AttemptRecord:
     # Rate limiting for resource-intensive scoring endpoint
@@ -117,7 +191,8 @@ async def score(payload: ScoreRequest, request: Request) -> AttemptRecord:
     check_rate_limit("/score", client_ip)
 
     result = await score_answer(payload)
-    return await _database.record_attempt(payload, result)
+    read_only = getattr(request.app.state, "read_only", False)
+    return await _database.record_attempt(payload, result, save=not read_only)
 
 
 @app.get("/decks", response_model=List[DeckRecord])
@@ -264,8 +264,11 @@ class Database:
         await conn.execute("DELETE FROM decks WHERE id = ?", (deck_id,))
         await conn.commit()
 
-    async def record_attempt(self, request: ScoreRequest, result: ScoreResult) -> AttemptRecord:
-        """Persist a study attempt and return the enriched record."""
+    async def record_attempt(self, request: ScoreRequest, result: ScoreResult, save: bool = True) -> AttemptRecord:
+        """Persist a study attempt and return the enriched record.
+
+        With ``save=False`` the record is built but not written, for a read-only backend.
+        """
 
         conn = self._require_connection()
         attempt_id = str(uuid4())
@@ -280,22 +283,23 @@ class Database:
             "coverage": result.coverage,
             "verdict": result.verdict.value
         }
-        await conn.execute(
-            """
-            INSERT INTO attempts (id, card_id, user_answer, verdict, score, created_at, payload)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
-            """,
-            (
-                attempt_id,
-                request.card_id,
-                request.user_answer,
-                result.verdict.value,
-                result.score,
-                timestamp,
-                json.dumps(payload)
+        if save:
+            await conn.execute(
+                """
+                INSERT INTO attempts (id, card_id, user_answer, verdict, score, created_at, payload)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                """,
+                (
+                    attempt_id,
+                    request.card_id,
+                    request.user_answer,
+                    result.verdict.value,
+                    result.score,
+                    timestamp,
+                    json.dumps(payload)
+                )
             )
-        )
-        await conn.commit()
+            await conn.commit()
         return AttemptRecord(
             id=attempt_id,
             card_id=request.card_id,
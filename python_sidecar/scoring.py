@@ -2,6 +2,7 @@ from __future__ import annotations
 
 import asyncio
 import json
+import os
 import sys
 from dataclasses import dataclass
 from pathlib import Path
@@ -15,9 +16,44 @@ from .models import ScoreRequest, ScoreResult, Verdict
 MODEL_NAME = "sentence-transformers/all-MiniLM-L6-v2"
 MODEL_CACHE_DIR = DATA_DIR / "models"
 
+# Where the model runs, set from --device by select_device; None lets
+# sentence-transformers pick
+DEVICE: Optional[str] = None
+
+
+def select_device(requested: str) -> str:
+    """Resolve --device ("auto", "cpu" or "cuda") to the device to use.
+
+    "cuda" without a usable GPU falls back to the CPU rather than failing
+    to load the model.
+    """
+    global DEVICE
+    if requested == "cpu":
+        DEVICE = "cpu"
+        return DEVICE
+    try:
+        import torch
+
+        gpu = torch.cuda.is_available()
+    except Exception as e:
+        print(f"Could not probe for a GPU: {e}", file=sys.stderr, flush=True)
+        gpu = False
+    if not gpu and requested == "cuda":
+        print("CUDA was requested but no GPU is usable, using the CPU", file=sys.stderr, flush=True)
+    DEVICE = "cuda" if gpu else "cpu"
+    return DEVICE
+
 
 def _get_bundled_model_path() -> Optional[Path]:
     """Get the path to the bundled model if running as PyInstaller executable."""
+    # A model picked in the desktop app takes precedence
+    selected = os.getenv("FLASH_AI_MODEL_DIR")
+    if selected:
+        selected_path = Path(selected).expanduser()
+        if selected_path.exists():
+            return selected_path
+        print(f"FLASH_AI_MODEL_DIR {selected_path} does not exist, ignoring it", file=sys.stderr, flush=True)
+
     if getattr(sys, 'frozen', False) and hasattr(sys, '_MEIPASS'):
         # Running as PyInstaller bundle
         bundled_path = Path(sys._MEIPASS) / "models" / "sentence-transformers" / "all-MiniLM-L6-v2"
@@ -148,6 +184,9 @@ class EmbeddingScorer:
         if progress is not None:
             payload["progress"] = int(progress)
         print(f"[model] {detail}", flush=True)
+        if progress is not None:
+            # Read by the desktop app to drive the splash's loading bar
+            print(f"SIDECAR_LOADING={max(0, min(100, int(progress)))}", flush=True)
         print(f"[model-event]{json.dumps(payload, separators=(',', ':'))}", flush=True)
 
     def _download_model(self) -> None:
@@ -192,7 +231,7 @@ class EmbeddingScorer:
                 loading_msg = "Loading bundled embedding model..."
                 self._emit_model_event("loading", loading_msg, 50)
                 self._emit_model_event("initializing", "Initializing bundled model...", 75)
-                model = SentenceTransformer(str(bundled_model_path))
+                model = SentenceTransformer(str(bundled_model_path), device=DEVICE)
                 self._emit_model_event("ready", "Bundled embedding model ready", 100)
                 return model
 
@@ -208,7 +247,7 @@ class EmbeddingScorer:
                 self._emit_model_event("loading", loading_msg, 60)
                 self._emit_model_event("initializing", "Validating cached embedding model...", 75)
 
-            model = SentenceTransformer(MODEL_NAME, cache_folder=str(MODEL_CACHE_DIR))
+            model = SentenceTransformer(MODEL_NAME, cache_folder=str(MODEL_CACHE_DIR), device=DEVICE)
 
         except Exception as exc:
             error_message = f"Failed to prepare embeddings: {exc}"
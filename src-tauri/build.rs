@@ -1,3 +1,55 @@
+use std::path::Path;
+
+// Name of the bundled sidecar, exposed to the app as SIDECAR_BINARY.
+// FLASH_AI_SIDECAR_BINARY wins; otherwise it's the first externalBin entry
+// in tauri.conf.json, so renaming the binary there is all a fork needs.
+fn sidecar_binary() -> String {
+    println!("cargo:rerun-if-env-changed=FLASH_AI_SIDECAR_BINARY");
+    println!("cargo:rerun-if-changed=tauri.conf.json");
+    if let Ok(name) = std::env::var("FLASH_AI_SIDECAR_BINARY") {
+        return name;
+    }
+    let config: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string("tauri.conf.json").expect("Failed to read tauri.conf.json"))
+            .expect("Failed to parse tauri.conf.json");
+    config["bundle"]["externalBin"][0]
+        .as_str()
+        .and_then(|path| Path::new(path).file_name())
+        .and_then(|name| name.to_str())
+        .unwrap_or("retention-sidecar")
+        .to_string()
+}
+
+// Commit the app is built from, exposed as GIT_SHA for get_app_info.
+// FLASH_AI_GIT_SHA wins (CI can pass GITHUB_SHA through it); otherwise ask
+// git, and fall back to "unknown" outside a checkout.
+fn git_sha() -> String {
+    println!("cargo:rerun-if-env-changed=FLASH_AI_GIT_SHA");
+    if let Ok(sha) = std::env::var("FLASH_AI_GIT_SHA") {
+        return sha;
+    }
+    // Rebuild when HEAD moves, whether by checkout or by commit
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    if let Some(head_ref) = std::fs::read_to_string("../.git/HEAD")
+        .ok()
+        .and_then(|head| head.strip_prefix("ref: ").map(|r| r.trim().to_string()))
+    {
+        println!("cargo:rerun-if-changed=../.git/{}", head_ref);
+    }
+    std::process::Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|sha| sha.trim().to_string())
+        .filter(|sha| !sha.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
 fn main() {
+    println!("cargo:rustc-env=SIDECAR_BINARY={}", sidecar_binary());
+    println!("cargo:rustc-env=GIT_SHA={}", git_sha());
+    println!("cargo:rustc-env=TARGET_TRIPLE={}", std::env::var("TARGET").unwrap_or_default());
     tauri_build::build()
 }
@@ -0,0 +1,56 @@
+// App version and build metadata
+//
+// Lets the frontend show the version without hard-coding it in the JS
+// bundle, and pass it along for the sidecar version check. GIT_SHA and
+// TARGET_TRIPLE are set by build.rs.
+//
+// get_runtime_flags covers how this run was started, for the UI to adapt
+// to, e.g. disabling editing controls in demo mode.
+
+use crate::lock::LockExt;
+use crate::safe_mode::SafeMode;
+use crate::sidecar::{self, SidecarConfig};
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppInfo {
+    // From tauri.conf.json, falling back to Cargo.toml
+    pub version: String,
+    pub tauri_version: &'static str,
+    pub target_triple: &'static str,
+    // Short commit hash, or "unknown" when built outside a git checkout
+    pub git_sha: &'static str,
+    // "debug" or "release"
+    pub build_profile: &'static str,
+}
+
+#[tauri::command]
+pub fn get_app_info(app: AppHandle) -> AppInfo {
+    AppInfo {
+        version: app.package_info().version.to_string(),
+        tauri_version: tauri::VERSION,
+        target_triple: env!("TARGET_TRIPLE"),
+        git_sha: env!("GIT_SHA"),
+        build_profile: if cfg!(debug_assertions) { "debug" } else { "release" },
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeFlags {
+    // The backend rejects changes; advisory for the UI, which should hide
+    // or disable editing rather than rely on the errors
+    pub demo_mode: bool,
+    pub safe_mode: bool,
+    pub sidecar_disabled: bool,
+}
+
+#[tauri::command]
+pub fn get_runtime_flags(app: AppHandle) -> RuntimeFlags {
+    RuntimeFlags {
+        demo_mode: app.state::<Mutex<SidecarConfig>>().lock_or_recover().demo_mode,
+        safe_mode: app.state::<SafeMode>().0,
+        sidecar_disabled: sidecar::is_disabled(&app),
+    }
+}
@@ -0,0 +1,212 @@
+// User settings
+//
+// config.json in the app config dir holds both the sidecar and updater
+// settings and is what the frontend's settings page reads and writes.
+// Until it exists, the older per-area files (sidecar-config.json next to
+// the executable, updater.json) are still picked up. A field that's invalid
+// in any of them falls back to its own default; the rest are kept.
+//
+// The sections live on as separate managed states (`Mutex<SidecarConfig>`,
+// `Mutex<UpdaterSettings>`), which is what the rest of the app reads.
+
+use crate::lock::LockExt;
+use crate::logging;
+use crate::sidecar::{self, SidecarConfig};
+use crate::updater::UpdaterSettings;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+const CONFIG_FILE: &str = "config.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub sidecar: SidecarConfig,
+    pub updater: UpdaterSettings,
+}
+
+impl Config {
+    // Missing or unreadable files fall back to the defaults
+    pub fn load(app: &AppHandle) -> Self {
+        let Some(path) = config_path(app) else {
+            return Self::legacy(app);
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str::<Map<String, Value>>(&contents) {
+                Ok(mut sections) => Self {
+                    sidecar: sections
+                        .remove("sidecar")
+                        .map(|section| lenient(&path, section, SidecarConfig::invalid_fields))
+                        .unwrap_or_default(),
+                    updater: sections
+                        .remove("updater")
+                        .map(|section| lenient(&path, section, |_| Vec::new()))
+                        .unwrap_or_default(),
+                },
+                Err(e) => {
+                    warn!("Ignoring invalid {}: {}", path.display(), e);
+                    Self::default()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::legacy(app),
+            Err(e) => {
+                warn!("Failed to read {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    fn legacy(app: &AppHandle) -> Self {
+        Self {
+            sidecar: SidecarConfig::load(),
+            updater: UpdaterSettings::load(app),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.sidecar.validate()
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), String> {
+        let path = config_path(app).ok_or("Failed to resolve the app config dir")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+// Read one settings section on top of its defaults, field by field. A field
+// that doesn't deserialize, or that `invalid_fields` names, keeps its
+// default and is logged.
+pub fn lenient<T>(path: &Path, section: Value, invalid_fields: impl Fn(&T) -> Vec<(&'static str, String)>) -> T
+where
+    T: Default + Serialize + DeserializeOwned,
+{
+    let Value::Object(fields) = section else {
+        warn!("Ignoring {}: expected an object", path.display());
+        return T::default();
+    };
+    let Ok(Value::Object(defaults)) = serde_json::to_value(T::default()) else {
+        return T::default();
+    };
+    let mut merged = defaults.clone();
+    for (key, field) in fields {
+        let previous = merged.insert(key.clone(), field);
+        if let Err(e) = serde_json::from_value::<T>(Value::Object(merged.clone())) {
+            warn!("Ignoring {} in {}: {}", key, path.display(), e);
+            match previous {
+                Some(previous) => merged.insert(key, previous),
+                None => merged.remove(&key),
+            };
+        }
+    }
+
+    let config = serde_json::from_value::<T>(Value::Object(merged.clone())).unwrap_or_default();
+    let invalid = invalid_fields(&config);
+    if invalid.is_empty() {
+        return config;
+    }
+    for (key, e) in invalid {
+        warn!("Ignoring {} in {}: {}", key, path.display(), e);
+        match defaults.get(key) {
+            Some(default) => merged.insert(key.to_string(), default.clone()),
+            None => merged.remove(key),
+        };
+    }
+    serde_json::from_value(Value::Object(merged)).unwrap_or_default()
+}
+
+fn config_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join(CONFIG_FILE))
+}
+
+fn current(app: &AppHandle) -> Config {
+    Config {
+        sidecar: app
+            .state::<Mutex<SidecarConfig>>()
+            .lock_or_recover()
+            .clone(),
+        updater: app
+            .state::<Mutex<UpdaterSettings>>()
+            .lock_or_recover()
+            .clone(),
+    }
+}
+
+// Write the in-memory settings back to config.json. Callers must not hold
+// either settings lock.
+pub fn persist(app: &AppHandle) -> Result<(), String> {
+    current(app).save(app)
+}
+
+#[tauri::command]
+pub fn get_config(app: AppHandle) -> Config {
+    current(&app)
+}
+
+// Timeouts, the update channel and the log filter apply right away; poll
+// intervals and the sidecar list take effect on the next launch. Turning
+// the updater off applies right away, turning it back on needs a relaunch.
+// Switching demo mode restarts the backend. Startup metrics are the app's
+// to record, so the ones passed in are ignored.
+#[tauri::command]
+pub fn set_config(app: AppHandle, mut config: Config) -> Result<(), String> {
+    config.validate()?;
+    let (startup_metrics, demo_mode) = {
+        let current = app.state::<Mutex<SidecarConfig>>();
+        let current = current.lock_or_recover();
+        (current.startup_metrics.clone(), current.demo_mode)
+    };
+    config.sidecar.startup_metrics = startup_metrics;
+    config.save(&app)?;
+    logging::apply_config_filter(&app, config.sidecar.log_filter.as_deref());
+    let restart = config.sidecar.demo_mode != demo_mode;
+    *app.state::<Mutex<SidecarConfig>>().lock_or_recover() = config.sidecar;
+    *app.state::<Mutex<UpdaterSettings>>().lock_or_recover() = config.updater;
+    if restart {
+        sidecar::restart_backend_if_running(&app);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn lenient_keeps_valid_fields_and_defaults_invalid_ones() {
+        let section = json!({
+            "startup_timeout_secs": 0,
+            "health_check_interval_secs": 0,
+            "crash_grace_ms": "soon",
+            "max_restarts": 2,
+        });
+        let config: SidecarConfig = lenient(Path::new("config.json"), section, SidecarConfig::invalid_fields);
+        let defaults = SidecarConfig::default();
+        assert_eq!(config.startup_timeout_secs, defaults.startup_timeout_secs);
+        assert_eq!(config.crash_grace_ms, defaults.crash_grace_ms);
+        assert_eq!(config.health_check_interval_secs, 0);
+        assert_eq!(config.max_restarts, 2);
+    }
+
+    #[test]
+    fn zero_intervals_are_valid() {
+        let mut config = Config::default();
+        config.sidecar.health_check_interval_secs = 0;
+        config.updater.update_check_interval_hours = 0;
+        assert!(config.validate().is_ok());
+    }
+}
@@ -0,0 +1,102 @@
+// Deep links
+//
+// `flashai://open/report/123?tab=notes` opens the app at a given view. The
+// scheme is registered by the installer; on Linux and in Windows dev runs
+// it's registered at startup instead. A link opened while the app is
+// already running starts a second instance, which single-instance turns
+// into an open-url event on this one, so either way the link ends up in
+// `handle_urls`.
+//
+// The link the app was launched with arrives before the frontend listens,
+// so it's also kept for take_pending_deep_link.
+
+use crate::events::{self, AppEvent};
+use crate::lock::LockExt;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+pub const SCHEME: &str = "flashai";
+
+// Payload of `deep-link`. For `flashai://open/report/123?tab=notes`, `path`
+// is "open/report/123", `segments` its parts and `params` {"tab": "notes"}.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepLink {
+    url: String,
+    path: String,
+    segments: Vec<String>,
+    params: HashMap<String, String>,
+}
+
+// The launch link, until the frontend takes it
+pub struct PendingDeepLink(pub Mutex<Option<DeepLink>>);
+
+pub fn init(app: &AppHandle) {
+    #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+    if let Err(e) = app.deep_link().register_all() {
+        tracing::warn!("Failed to register the {} scheme: {}", SCHEME, e);
+    }
+
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| handle_urls(&handle, event.urls(), true));
+
+    // The window is still hidden behind the splash at launch, so don't
+    // focus it; the link is kept until the frontend asks for it
+    match app.deep_link().get_current() {
+        Ok(Some(urls)) => handle_urls(app, urls, false),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to read the launch deep link: {}", e),
+    }
+}
+
+// The link the app was launched with, if the frontend hasn't taken it yet
+#[tauri::command]
+pub fn take_pending_deep_link(pending: tauri::State<PendingDeepLink>) -> Option<DeepLink> {
+    pending.0.lock_or_recover().take()
+}
+
+fn handle_urls(app: &AppHandle, urls: Vec<Url>, running: bool) {
+    let links: Vec<DeepLink> = urls.iter().filter_map(parse).collect();
+    let Some(last) = links.last() else {
+        return;
+    };
+    if running {
+        crate::tray::show_main_window(app);
+    } else {
+        *app.state::<PendingDeepLink>().0.lock_or_recover() = Some(last.clone());
+    }
+    for link in links {
+        tracing::info!(path = %link.path, "Opening deep link");
+        events::emit(app, AppEvent::DeepLink(link));
+    }
+}
+
+// Anything that isn't `flashai://<path>` is logged and dropped
+fn parse(url: &Url) -> Option<DeepLink> {
+    if url.scheme() != SCHEME {
+        tracing::warn!(%url, "Ignoring deep link with an unknown scheme");
+        return None;
+    }
+    // The first segment parses as the host. Segments are passed on as they
+    // appear in the URL, still percent-encoded.
+    let segments: Vec<String> = url
+        .host_str()
+        .into_iter()
+        .chain(url.path_segments().into_iter().flatten())
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+        .collect();
+    if segments.is_empty() {
+        tracing::warn!(%url, "Ignoring deep link without a path");
+        return None;
+    }
+    Some(DeepLink {
+        url: url.to_string(),
+        path: segments.join("/"),
+        segments,
+        params: url.query_pairs().into_owned().collect(),
+    })
+}
+
@@ -0,0 +1,97 @@
+// Support bundle export
+//
+// Zips the app and sidecar logs together with a diagnostics.json describing
+// the app version, platform, sidecar status and config, so a bug report
+// needs only one attachment.
+
+use crate::lock::LockExt;
+use crate::safe_mode::SafeMode;
+use crate::sidecar::{SidecarConfig, SidecarStatus, SidecarStatusState};
+use crate::updater::UpdaterSettings;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+#[derive(Serialize)]
+struct Diagnostics {
+    generated_at: String,
+    app_version: String,
+    tauri_version: &'static str,
+    os: &'static str,
+    os_version: Option<String>,
+    arch: &'static str,
+    // The config below is the default one in safe mode, not the saved one
+    safe_mode: bool,
+    sidecars: HashMap<String, SidecarStatus>,
+    sidecar_config: SidecarConfig,
+    updater_settings: UpdaterSettings,
+}
+
+// Ask where to save the bundle and write it there. Returns the path, or
+// None if the user cancelled the dialog.
+#[tauri::command]
+pub async fn export_diagnostics(app: AppHandle) -> Result<Option<String>, String> {
+    let file_name = format!("retention-diagnostics-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    let Some(path) = app
+        .dialog()
+        .file()
+        .set_file_name(file_name)
+        .add_filter("Zip archive", &["zip"])
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = path.into_path().map_err(|e| format!("Invalid save location: {}", e))?;
+
+    write_bundle(&app, &path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    tracing::info!(path = %path.display(), "Exported diagnostics");
+    Ok(Some(path.display().to_string()))
+}
+
+fn write_bundle(app: &AppHandle, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default();
+
+    zip.start_file("diagnostics.json", options)?;
+    zip.write_all(serde_json::to_string_pretty(&collect(app))?.as_bytes())?;
+
+    // Both our own log and the sidecar logs, including rotated files
+    if let Ok(log_dir) = app.path().app_log_dir() {
+        if let Ok(entries) = std::fs::read_dir(&log_dir) {
+            for entry in entries.flatten() {
+                let entry_path = entry.path();
+                if !entry_path.is_file() {
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy().into_owned();
+                zip.start_file(format!("logs/{}", name), options)?;
+                std::io::copy(&mut File::open(&entry_path)?, &mut zip)?;
+            }
+        }
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+fn collect(app: &AppHandle) -> Diagnostics {
+    Diagnostics {
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        app_version: app.package_info().version.to_string(),
+        tauri_version: tauri::VERSION,
+        os: std::env::consts::OS,
+        os_version: sysinfo::System::long_os_version(),
+        arch: std::env::consts::ARCH,
+        safe_mode: app.state::<SafeMode>().0,
+        sidecars: app.state::<SidecarStatusState>().0.lock_or_recover().clone(),
+        sidecar_config: app.state::<Mutex<SidecarConfig>>().lock_or_recover().clone(),
+        updater_settings: app.state::<Mutex<UpdaterSettings>>().lock_or_recover().clone(),
+    }
+}
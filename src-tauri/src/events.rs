@@ -0,0 +1,125 @@
+// Events sent to the frontend
+//
+// Everything the app emits goes through `emit`, which sends each event
+// twice: under its own name (`sidecar-ready`, `update-progress`, ...) with
+// the bare payload, as before, and on the single `app-event` channel as
+// `{ "type": "sidecarReady", "payload": { ... } }`, so the UI can subscribe
+// once and match on `type`. This enum is the full list of what can be sent.
+
+use crate::deep_link::DeepLink;
+use crate::first_run::FirstRun;
+use crate::models::ModelChanged;
+use crate::sidecar::{
+    SidecarCrashed, SidecarDeviceFallback, SidecarExited, SidecarGivingUp, SidecarHealth, SidecarLoading, SidecarLog,
+    SidecarPortChanged, SidecarPortConflict, SidecarReady, SidecarRestarting, SidecarSpawnRetry, SidecarStartupFailed,
+    SidecarUnhealthy,
+};
+use crate::sidecar_http::VersionMismatch;
+use crate::startup_report::{StartupComplete, StartupFailed};
+use crate::updater::{
+    UpdateDownloadComplete, UpdateDrainTimeout, UpdateFailure, UpdateInfo, UpdateInstallComplete, UpdateProgress,
+};
+use crate::SecondInstance;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+pub const APP_EVENT: &str = "app-event";
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all = "camelCase")]
+pub enum AppEvent {
+    FirstRun(FirstRun),
+    SecondInstance(SecondInstance),
+    DeepLink(DeepLink),
+    SidecarSpawnRetry(SidecarSpawnRetry),
+    SidecarPortConflict(SidecarPortConflict),
+    SidecarPortChanged(SidecarPortChanged),
+    SidecarDeviceFallback(SidecarDeviceFallback),
+    SidecarLoading(SidecarLoading),
+    SidecarReady(SidecarReady),
+    SidecarStartupFailed(SidecarStartupFailed),
+    StartupComplete(StartupComplete),
+    StartupFailed(StartupFailed),
+    // Extra sidecars' lines are prefixed with `[name] `
+    SidecarStderr(String),
+    SidecarLog(SidecarLog),
+    SidecarHealth(SidecarHealth),
+    SidecarUnhealthy(SidecarUnhealthy),
+    HealthChecksPaused,
+    HealthChecksResumed,
+    SidecarExited(SidecarExited),
+    SidecarCrashed(SidecarCrashed),
+    SidecarRestarting(SidecarRestarting),
+    SidecarGivingUp(SidecarGivingUp),
+    VersionMismatch(VersionMismatch),
+    ModelChanged(ModelChanged),
+    UpdateAvailable(UpdateInfo),
+    UpdateProgress(UpdateProgress),
+    // Deprecated: sent when the last byte arrives, before the artifact is
+    // saved. Use UpdateDownloadComplete.
+    UpdateDownloaded,
+    UpdateDownloadComplete(UpdateDownloadComplete),
+    UpdateCancelled,
+    UpdateDrainTimeout(UpdateDrainTimeout),
+    UpdateError(UpdateFailure),
+    // The installed version. Superseded by UpdateInstallComplete, which
+    // also says whether the app is relaunching.
+    UpdateInstalled(String),
+    UpdateInstallComplete(UpdateInstallComplete),
+}
+
+impl AppEvent {
+    // Name of the granular event
+    pub fn name(&self) -> &'static str {
+        match self {
+            AppEvent::FirstRun(_) => "first-run",
+            AppEvent::SecondInstance(_) => "second-instance",
+            AppEvent::DeepLink(_) => "deep-link",
+            AppEvent::SidecarSpawnRetry(_) => "sidecar-spawn-retry",
+            AppEvent::SidecarPortConflict(_) => "sidecar-port-conflict",
+            AppEvent::SidecarPortChanged(_) => "sidecar-port-changed",
+            AppEvent::SidecarDeviceFallback(_) => "sidecar-device-fallback",
+            AppEvent::SidecarLoading(_) => "sidecar-loading",
+            AppEvent::SidecarReady(_) => "sidecar-ready",
+            AppEvent::SidecarStartupFailed(_) => "sidecar-startup-failed",
+            AppEvent::StartupComplete(_) => "startup-complete",
+            AppEvent::StartupFailed(_) => "startup-failed",
+            AppEvent::SidecarStderr(_) => "sidecar-stderr",
+            AppEvent::SidecarLog(_) => "sidecar-log",
+            AppEvent::SidecarHealth(_) => "sidecar-health",
+            AppEvent::SidecarUnhealthy(_) => "sidecar-unhealthy",
+            AppEvent::HealthChecksPaused => "health-checks-paused",
+            AppEvent::HealthChecksResumed => "health-checks-resumed",
+            AppEvent::SidecarExited(_) => "sidecar-exited",
+            AppEvent::SidecarCrashed(_) => "sidecar-crashed",
+            AppEvent::SidecarRestarting(_) => "sidecar-restarting",
+            AppEvent::SidecarGivingUp(_) => "sidecar-giving-up",
+            AppEvent::VersionMismatch(_) => "version-mismatch",
+            AppEvent::ModelChanged(_) => "model-changed",
+            AppEvent::UpdateAvailable(_) => "update-available",
+            AppEvent::UpdateProgress(_) => "update-progress",
+            AppEvent::UpdateDownloaded => "update-downloaded",
+            AppEvent::UpdateDownloadComplete(_) => "update-download-complete",
+            AppEvent::UpdateCancelled => "update-cancelled",
+            AppEvent::UpdateDrainTimeout(_) => "update-drain-timeout",
+            AppEvent::UpdateError(_) => "update-error",
+            AppEvent::UpdateInstalled(_) => "update-installed",
+            AppEvent::UpdateInstallComplete(_) => "update-install-complete",
+        }
+    }
+}
+
+// Serialized once; the granular event gets the `payload` part, or null for
+// events without one. Failures (e.g. no window yet) are ignored.
+pub fn emit(app: &AppHandle, event: AppEvent) {
+    let value = match serde_json::to_value(&event) {
+        Ok(value) => value,
+        Err(e) => {
+            tracing::warn!(event = event.name(), "Failed to serialize event: {}", e);
+            return;
+        }
+    };
+    let payload = value.get("payload").cloned().unwrap_or_default();
+    let _ = app.emit(event.name(), payload);
+    let _ = app.emit(APP_EVENT, value);
+}
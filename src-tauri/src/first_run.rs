@@ -0,0 +1,78 @@
+// First launch and upgrade detection
+//
+// The app config dir holds a `last-version` file naming the version that
+// last ran. No file means this is the first launch; a different version
+// means an upgrade. Either way `first-run` is sent so the UI can show
+// onboarding or what's new, and the file is brought up to date afterwards.
+//
+// This runs during setup, before the frontend listens, so the payload is
+// also kept for take_first_run.
+
+use crate::events::{self, AppEvent};
+use crate::lock::LockExt;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+const LAST_VERSION_FILE: &str = "last-version";
+
+// Payload of `first-run`. `previous_version` is None on a fresh install.
+#[derive(Debug, Clone, Serialize)]
+pub struct FirstRun {
+    version: String,
+    previous_version: Option<String>,
+}
+
+// This launch's FirstRun, until the frontend takes it
+pub struct PendingFirstRun(pub Mutex<Option<FirstRun>>);
+
+pub fn check(app: &AppHandle) {
+    let dir = match app.path().app_config_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            tracing::warn!("Failed to resolve the app config dir: {}", e);
+            return;
+        }
+    };
+    // Missing on the very first launch
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        tracing::warn!("Failed to create {}: {}", dir.display(), e);
+        return;
+    }
+    let path = dir.join(LAST_VERSION_FILE);
+    let previous_version = match std::fs::read_to_string(&path) {
+        Ok(contents) => Some(contents.trim().to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            // Better to skip onboarding than to show it on every launch
+            tracing::warn!("Failed to read {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let version = app.package_info().version.to_string();
+    if previous_version.as_deref() == Some(version.as_str()) {
+        return;
+    }
+
+    match &previous_version {
+        Some(previous) => tracing::info!(%previous, "First launch of this version"),
+        None => tracing::info!("First launch"),
+    }
+    let first_run = FirstRun {
+        version: version.clone(),
+        previous_version,
+    };
+    *app.state::<PendingFirstRun>().0.lock_or_recover() = Some(first_run.clone());
+    events::emit(app, AppEvent::FirstRun(first_run));
+
+    if let Err(e) = std::fs::write(&path, &version) {
+        tracing::warn!("Failed to write {}: {}", path.display(), e);
+    }
+}
+
+// This launch's `first-run` payload, if there was one and the frontend
+// hasn't taken it yet
+#[tauri::command]
+pub fn take_first_run(pending: tauri::State<PendingFirstRun>) -> Option<FirstRun> {
+    pending.0.lock_or_recover().take()
+}
@@ -0,0 +1,91 @@
+// Headless mode
+//
+// `--headless` runs the backend without a window, tray or update checks,
+// for server-style use and tests. Once the backend is ready its port is
+// printed to stdout as `SIDECAR_PORT=<port>`; the app then runs until it
+// gets Ctrl+C (SIGINT), when it stops the sidecars and exits.
+//
+// Exit codes:
+// - 0: stopped with Ctrl+C
+// - 1: the backend failed to start
+// - 2: sidecars are disabled (FLASH_AI_NO_SIDECAR or `disable_sidecar`),
+//   so there's nothing to run
+
+use crate::lock::LockExt;
+use crate::sidecar::{self, SidecarStatus, SidecarStatusChanged, SidecarStatusState, DEFAULT_SIDECAR};
+use crate::startup_report;
+use tauri::{AppHandle, Manager};
+
+pub const HEADLESS_FLAG: &str = "--headless";
+pub const EXIT_STARTUP_FAILED: i32 = 1;
+pub const EXIT_NO_SIDECAR: i32 = 2;
+
+// Read in main, before the builder, since it decides whether there's a window
+pub fn requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == HEADLESS_FLAG)
+}
+
+// Release builds on Windows are GUI apps without a console of their own, so
+// borrow the one we were started from for the port and error output
+pub fn attach_console() {
+    #[cfg(all(windows, not(debug_assertions)))]
+    unsafe {
+        use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+// Called at the end of setup, after the sidecars were spawned
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if sidecar::is_disabled(&app) {
+            eprintln!("Sidecars are disabled, nothing to run");
+            crate::exit(&app, EXIT_NO_SIDECAR).await;
+            return;
+        }
+        match wait_for_backend(&app).await {
+            Ok(port) => {
+                tracing::info!(port, "Running headless");
+                println!("SIDECAR_PORT={}", port);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                crate::exit(&app, EXIT_STARTUP_FAILED).await;
+                return;
+            }
+        }
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            return;
+        }
+        tracing::info!("Got Ctrl+C");
+        crate::exit(&app, 0).await;
+    });
+}
+
+// The backend's port once it's running. Startup retries and crash restarts
+// run their course first, so a crash on its own isn't a failure; an error
+// once it's given up (`startup-failed`).
+async fn wait_for_backend(app: &AppHandle) -> Result<u16, String> {
+    let changed = app.state::<SidecarStatusChanged>();
+    let mut failures = startup_report::failures(app);
+    loop {
+        // Register before checking so a change in between isn't missed
+        let notified = changed.0.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if let Some(SidecarStatus::Running(port)) =
+            app.state::<SidecarStatusState>().0.lock_or_recover().get(DEFAULT_SIDECAR)
+        {
+            return Ok(*port);
+        }
+        if let Some(error) = failures.borrow_and_update().clone() {
+            return Err(format!("The backend failed to start: {}", error));
+        }
+        tokio::select! {
+            _ = notified => {}
+            _ = failures.changed() => {}
+        }
+    }
+}
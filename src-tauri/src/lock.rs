@@ -0,0 +1,44 @@
+// Poison-tolerant locking
+//
+// A panic while a guard is held poisons the mutex, and `lock().unwrap()`
+// would then bring the whole app down on the next access. The state behind
+// these locks is plain data that is never left half-updated across a
+// panic point, so it's safe to keep using it.
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+pub trait LockExt<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockExt<T> for Mutex<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn recovers_a_poisoned_mutex() {
+        let mutex = Arc::new(Mutex::new(vec![1]));
+        let poisoner = Arc::clone(&mutex);
+        let result = std::thread::spawn(move || {
+            let mut values = poisoner.lock().unwrap();
+            values.push(2);
+            panic!("poison the mutex");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(mutex.is_poisoned());
+
+        let mut values = mutex.lock_or_recover();
+        assert_eq!(*values, vec![1, 2]);
+        values.push(3);
+        drop(values);
+        assert_eq!(*mutex.lock_or_recover(), vec![1, 2, 3]);
+    }
+}
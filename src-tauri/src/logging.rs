@@ -0,0 +1,71 @@
+// App logging
+//
+// Events go to stderr and to a daily-rotated retention.log in the app log
+// dir, which is the only place they're visible in the windowed release
+// build. RUST_LOG takes precedence over the `log_filter` config field.
+
+use tauri::{AppHandle, Manager};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+const DEFAULT_LOG_FILTER: &str = "info";
+const APP_LOG_PREFIX: &str = "retention";
+const APP_LOG_MAX_FILES: usize = 7;
+
+pub struct Logging {
+    filter: reload::Handle<EnvFilter, Registry>,
+    // Set when RUST_LOG was given, so the config doesn't override it
+    from_env: bool,
+}
+
+pub fn init(app: &AppHandle) {
+    let (filter, from_env) = match EnvFilter::try_from_default_env() {
+        Ok(filter) => (filter, true),
+        Err(_) => (EnvFilter::new(DEFAULT_LOG_FILTER), false),
+    };
+    let (filter, filter_handle) = reload::Layer::new(filter);
+
+    let file = app.path().app_log_dir().ok().and_then(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(APP_LOG_PREFIX)
+            .filename_suffix("log")
+            .max_log_files(APP_LOG_MAX_FILES)
+            .build(dir)
+            .map_err(|e| eprintln!("Failed to open the app log file: {}", e))
+            .ok()
+    });
+
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        // Written synchronously so nothing is lost when the app exits
+        .with(file.map(|file| fmt::layer().with_ansi(false).with_writer(file)));
+    if let Err(e) = registry.try_init() {
+        eprintln!("Failed to initialize logging: {}", e);
+    }
+
+    app.manage(Logging {
+        filter: filter_handle,
+        from_env,
+    });
+}
+
+// Switch to the filter from the config, e.g. "debug" or "retention=trace"
+pub fn apply_config_filter(app: &AppHandle, directives: Option<&str>) {
+    let logging = app.state::<Logging>();
+    if logging.from_env {
+        return;
+    }
+    let directives = directives.unwrap_or(DEFAULT_LOG_FILTER);
+    match EnvFilter::try_new(directives) {
+        Ok(filter) => {
+            if let Err(e) = logging.filter.reload(filter) {
+                tracing::warn!("Failed to apply log filter {:?}: {}", directives, e);
+            }
+        }
+        Err(e) => tracing::warn!("Ignoring invalid log filter {:?}: {}", directives, e),
+    }
+}
@@ -1,9 +1,51 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod app_info;
+mod config;
+mod deep_link;
+mod diagnostics;
+mod events;
+mod first_run;
+mod headless;
+mod lock;
+mod logging;
+mod models;
+mod orphans;
+mod panic_hook;
+mod rotating_log;
+mod safe_mode;
+mod self_test;
+mod sidecar;
+mod sidecar_http;
+mod splash;
+mod startup_metrics;
+mod startup_report;
+mod tray;
+mod update_history;
+mod updater;
+mod window_state;
+
+use config::Config;
+use deep_link::PendingDeepLink;
+use first_run::PendingFirstRun;
+use safe_mode::SafeMode;
+use events::AppEvent;
+use serde::Serialize;
+use sidecar::{
+    HealthChecksPaused, HealthPoller, SidecarAddr, SidecarChild, SidecarCliArgs, SidecarCommandLine, SidecarCrashes,
+    SidecarDisabled, SidecarEnv, SidecarEnvOverrides, SidecarInfo, SidecarLastPort, SidecarLazy, SidecarLogFile,
+    SidecarLogSeq, SidecarLogs, SidecarSpawning, SidecarStatusChanged, SidecarStatusState, SidecarStopping,
+    SidecarSystem, SidecarToken,
+};
+use sidecar_http::SidecarHttp;
+use startup_report::StartupReport;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use tauri::{Manager, State, Emitter};
-use tauri_plugin_shell::ShellExt;
-use tauri_plugin_updater::UpdaterExt;
+use tauri::{AppHandle, Manager, WindowEvent};
+use tokio::sync::{watch, Notify};
+use updater::{PendingUpdate, UpdateCancel, UpdateCheckLock, UpdatePoller};
+use window_state::WindowStateGeneration;
 
 // Flash-AI Tauri Application
 // This is a Tauri wrapper that provides a desktop window for the React frontend
@@ -15,135 +57,270 @@ use tauri_plugin_updater::UpdaterExt;
 //
 // The sidecar is automatically started when the app launches and stopped when it closes.
 
-struct SidecarPort(Arc<Mutex<Option<u16>>>);
+// Payload of `second-instance`: the args a second launch was started with
+#[derive(Clone, Serialize)]
+pub struct SecondInstance {
+    args: Vec<String>,
+    cwd: String,
+}
 
-#[tauri::command]
-fn get_sidecar_port(port_state: State<SidecarPort>) -> Option<u16> {
-    *port_state.0.lock().unwrap()
+// Stop background work and the sidecars before the app exits
+pub async fn shutdown(app: &AppHandle) {
+    tracing::info!("Shutting down");
+    sidecar::stop_health_poller(app);
+    updater::stop_update_poller(app);
+    sidecar::shutdown_all_sidecars(app).await;
 }
 
-#[tauri::command]
-async fn check_for_updates(app: tauri::AppHandle) -> Result<String, String> {
-    match app.updater() {
-        Ok(updater) => {
-            match updater.check().await {
-                Ok(Some(update)) => {
-                    Ok(format!("Update available: v{}", update.version))
-                },
-                Ok(None) => Ok("No updates available".to_string()),
-                Err(e) => Err(format!("Failed to check for updates: {}", e)),
-            }
-        }
-        Err(e) => Err(format!("Failed to get updater: {}", e)),
+// Undo `shutdown` when the app isn't exiting after all, e.g. because an
+// update failed to install
+pub fn resume(app: &AppHandle) {
+    tracing::info!("Resuming after an aborted shutdown");
+    sidecar::resume_sidecars(app);
+    updater::start_update_poller(app);
+}
+
+// For window handlers, which run outside the async runtime
+pub fn shutdown_blocking(app: &AppHandle) {
+    tauri::async_runtime::block_on(shutdown(app));
+}
+
+// Set by the first `quit`; later calls leave the exit to that one
+pub struct Quitting(pub AtomicBool);
+
+// Shut down and exit the app. Unlike closing the main window, this always
+// exits, so the tray and the frontend both quit through here.
+pub async fn quit(app: &AppHandle) {
+    exit(app, 0).await;
+}
+
+// `quit` with an exit code, for headless runs
+pub async fn exit(app: &AppHandle, code: i32) {
+    if app.state::<Quitting>().0.swap(true, Ordering::SeqCst) {
+        return;
     }
+    shutdown(app).await;
+    sidecar::close_log_files(app);
+    tracing::info!(code, "Exiting");
+    app.exit(code);
 }
 
 #[tauri::command]
-async fn install_update(app: tauri::AppHandle) -> Result<String, String> {
-    match app.updater() {
-        Ok(updater) => {
-            match updater.check().await {
-                Ok(Some(update)) => {
-                    // Download and install the update
-                    match update.download_and_install(|chunk_length, content_length| {
-                        // Emit progress events to the frontend
-                        if let Some(total) = content_length {
-                            let progress = (chunk_length as f64 / total as f64) * 100.0;
-                            let _ = app.emit("update-progress", progress);
-                        }
-                    }, || {
-                        // Called when download is finished
-                        let _ = app.emit("update-downloaded", ());
-                    }).await {
-                        Ok(_) => Ok("Update installed successfully. Please restart the application.".to_string()),
-                        Err(e) => Err(format!("Failed to install update: {}", e)),
-                    }
-                },
-                Ok(None) => Err("No updates available".to_string()),
-                Err(e) => Err(format!("Failed to check for updates: {}", e)),
-            }
-        }
-        Err(e) => Err(format!("Failed to get updater: {}", e)),
-    }
+async fn quit_app(app: AppHandle) {
+    quit(&app).await;
 }
 
 fn main() {
-    let sidecar_port = Arc::new(Mutex::new(None));
-    let port_clone = Arc::clone(&sidecar_port);
-
+    panic_hook::install();
+    let headless = headless::requested();
+    if headless {
+        headless::attach_console();
+    }
     tauri::Builder::default()
+        // Must be registered first: a second launch exits here, before setup
+        // runs, so only the primary instance ever spawns a sidecar
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+            events::emit(app, AppEvent::SecondInstance(SecondInstance { args, cwd }));
+        }))
+        // After single-instance, which forwards links opened while running
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_http::init())
+        .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
-        .plugin(tauri_plugin_updater::Builder::new().build())
-        .manage(SidecarPort(sidecar_port))
+        .manage(SidecarStatusState(Arc::new(Mutex::new(HashMap::new()))))
+        .manage(SidecarStatusChanged(Notify::new()))
+        .manage(SidecarAddr(watch::channel(HashMap::new()).0))
+        .manage(SidecarLastPort(Mutex::new(HashMap::new())))
+        .manage(SidecarChild(Arc::new(Mutex::new(HashMap::new()))))
+        .manage(SidecarCrashes(Mutex::new(HashMap::new())))
+        .manage(SidecarCommandLine(Mutex::new(HashMap::new())))
+        .manage(SidecarLogs(Arc::new(Mutex::new(HashMap::new()))))
+        .manage(SidecarLogFile(Mutex::new(HashMap::new())))
+        .manage(SidecarLogSeq(AtomicU64::new(0)))
+        .manage(SidecarStopping(Mutex::new(HashSet::new())))
+        .manage(SidecarSpawning(Arc::new(Mutex::new(HashSet::new()))))
+        .manage(SidecarInfo(Mutex::new(HashMap::new())))
+        .manage(SidecarLazy(Mutex::new(HashSet::new())))
+        .manage(SidecarEnvOverrides(Mutex::new(HashMap::new())))
+        .manage(HealthPoller(Mutex::new(None)))
+        .manage(HealthChecksPaused(watch::channel(Default::default()).0))
+        .manage(PendingUpdate(Mutex::new(None)))
+        .manage(UpdateCheckLock(tokio::sync::Mutex::new(())))
+        .manage(UpdateCancel(Mutex::new(None)))
+        .manage(UpdatePoller(Mutex::new(None)))
+        .manage(WindowStateGeneration(AtomicU64::new(0)))
+        .manage(Quitting(AtomicBool::new(false)))
+        .manage(PendingDeepLink(Mutex::new(None)))
+        .manage(PendingFirstRun(Mutex::new(None)))
+        .manage(SidecarToken::generate())
+        .manage(SidecarHttp::new())
+        .manage(StartupReport::new())
+        .manage(SidecarSystem(Mutex::new(sysinfo::System::new())))
         .invoke_handler(tauri::generate_handler![
-            get_sidecar_port,
-            check_for_updates,
-            install_update
+            sidecar::get_sidecar_status,
+            sidecar::get_sidecar_port,
+            sidecar::wait_for_sidecar,
+            sidecar::get_sidecar_addr,
+            sidecar::get_sidecar_pid,
+            sidecar::get_sidecar_token,
+            sidecar::get_sidecar_info,
+            sidecar::get_sidecar_logs,
+            sidecar::reveal_sidecar_logs,
+            sidecar::get_sidecar_resources,
+            sidecar::list_processes,
+            sidecar::get_sidecar_command_info,
+            sidecar::check_sidecar_health,
+            sidecar::pause_health_checks,
+            sidecar::resume_health_checks,
+            sidecar::set_startup_timeout,
+            sidecar::start_sidecar,
+            sidecar::stop_sidecar,
+            sidecar::restart_sidecar,
+            sidecar::restart_sidecar_with_env,
+            sidecar::set_sidecar_log_level,
+            sidecar::set_device,
+            sidecar::set_demo_mode,
+            sidecar::restart_sidecar_clearing_cache,
+            startup_metrics::get_startup_metrics,
+            sidecar::move_sidecar_to_new_port,
+            models::list_models,
+            models::select_model,
+            sidecar_http::sidecar_request,
+            sidecar_http::get_sidecar_version,
+            app_info::get_app_info,
+            app_info::get_runtime_flags,
+            deep_link::take_pending_deep_link,
+            first_run::take_first_run,
+            self_test::run_self_test,
+            safe_mode::is_safe_mode,
+            safe_mode::restart_in_safe_mode,
+            config::get_config,
+            config::set_config,
+            diagnostics::export_diagnostics,
+            updater::get_update_channel,
+            updater::set_update_channel,
+            updater::get_update_endpoint,
+            updater::remind_me_later,
+            updater::check_for_updates,
+            updater::check_update_connectivity,
+            updater::get_available_update,
+            updater::force_update_check,
+            updater::skip_update_version,
+            updater::download_update,
+            updater::apply_update,
+            updater::cancel_update,
+            updater::clear_pending_update,
+            updater::install_update,
+            updater::get_update_public_key,
+            updater::verify_update_signature,
+            updater::check_disk_space,
+            update_history::get_update_history,
+            quit_app
         ])
+        .on_window_event(|window, event| {
+            match event {
+                // Closing the splash means giving up on startup altogether
+                WindowEvent::CloseRequested { .. } if window.label() == splash::SPLASH_WINDOW => {
+                    shutdown_blocking(window.app_handle());
+                    window.app_handle().exit(0);
+                }
+                WindowEvent::CloseRequested { .. } if window.label() == "main" => shutdown_blocking(window.app_handle()),
+                WindowEvent::Resized(_) if window.label() == "main" => {
+                    window_state::schedule_save(window);
+                    // Minimizing shows up as a resize
+                    sidecar::set_window_minimized(window.app_handle(), window.is_minimized().unwrap_or(false));
+                }
+                WindowEvent::Moved(_) if window.label() == "main" => window_state::schedule_save(window),
+                _ => {}
+            }
+        })
         .setup(move |app| {
-            let shell = app.shell();
-
-            // Spawn the sidecar process
-            #[cfg(debug_assertions)]
-            {
-                // In dev mode, run the Python module directly
-                use tauri_plugin_shell::process::CommandEvent;
-
-                let (mut rx, _child) = shell
-                    .command("python")
-                    .args(["-m", "python_sidecar"])
-                    .spawn()
-                    .expect("Failed to spawn Python sidecar in dev mode");
-
-                // Read output to get the port
-                tauri::async_runtime::spawn(async move {
-                    while let Some(event) = rx.recv().await {
-                        if let CommandEvent::Stdout(line) = event {
-                            let line_str = String::from_utf8_lossy(&line);
-                            if line_str.starts_with("SIDECAR_PORT=") {
-                                if let Some(port_str) = line_str.strip_prefix("SIDECAR_PORT=") {
-                                    if let Ok(port) = port_str.trim().parse::<u16>() {
-                                        *port_clone.lock().unwrap() = Some(port);
-                                        println!("Sidecar started on port: {}", port);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                });
+            logging::init(app.handle());
+            panic_hook::set_app(app.handle());
+            tracing::info!(version = %app.package_info().version, "Starting Flash-AI");
+            let safe_mode = safe_mode::take_marker(app.handle());
+            app.manage(SafeMode(safe_mode));
+            let config = if safe_mode {
+                tracing::warn!("Starting in safe mode with the default config");
+                Config::default()
+            } else {
+                Config::load(app.handle())
+            };
+            logging::apply_config_filter(app.handle(), config.sidecar.log_filter.as_deref());
+            let sidecar_disabled = sidecar::disabled_by_config(&config.sidecar);
+            // Safe mode starts sidecars only when something needs them
+            // Headless runs exist to serve the backend, so they never defer it
+            let sidecar_lazy = !headless && (config.sidecar.lazy_sidecar || safe_mode);
+            app.manage(SidecarDisabled(sidecar_disabled));
+            app.manage(Mutex::new(config.sidecar));
+            if config.updater.updater_enabled {
+                app.handle().plugin(tauri_plugin_updater::Builder::new().build())?;
+            } else {
+                tracing::info!("Updater is disabled");
             }
+            app.manage(Mutex::new(config.updater));
 
-            #[cfg(not(debug_assertions))]
-            {
-                // In production, use the bundled sidecar binary
-                use tauri_plugin_shell::process::CommandEvent;
-
-                let (mut rx, _child) = shell
-                    .sidecar("retention-sidecar")
-                    .expect("Failed to create sidecar command")
-                    .spawn()
-                    .expect("Failed to spawn sidecar");
+            // The main window is created here rather than from the config
+            // (`create: false`), so headless runs never get one. It starts
+            // hidden; the splash shows it once the backend is up, at the
+            // saved geometry. Without sidecars, or with sidecars that only
+            // start on first use, there's nothing to wait for.
+            if !headless {
+                let config = app
+                    .config()
+                    .app
+                    .windows
+                    .iter()
+                    .find(|window| window.label == "main")
+                    .ok_or("tauri.conf.json has no main window")?;
+                let window = tauri::WebviewWindowBuilder::from_config(app.handle(), config)?.build()?;
+                window_state::restore(&window);
+                if sidecar_disabled || sidecar_lazy {
+                    let _ = window.show();
+                }
+            }
+            app.manage(SidecarEnv::load(app.handle()));
+            app.manage(SidecarCliArgs::from_args());
 
-                // Read output to get the port
-                tauri::async_runtime::spawn(async move {
-                    while let Some(event) = rx.recv().await {
-                        if let CommandEvent::Stdout(line) = event {
-                            let line_str = String::from_utf8_lossy(&line);
-                            if line_str.starts_with("SIDECAR_PORT=") {
-                                if let Some(port_str) = line_str.strip_prefix("SIDECAR_PORT=") {
-                                    if let Ok(port) = port_str.trim().parse::<u16>() {
-                                        *port_clone.lock().unwrap() = Some(port);
-                                        println!("Sidecar started on port: {}", port);
-                                    }
-                                }
-                            }
-                        }
+            if sidecar_disabled {
+                tracing::info!("Sidecars are disabled, not spawning any");
+                sidecar::mark_all_stopped(app.handle());
+            } else {
+                orphans::init(app.handle());
+                #[cfg(debug_assertions)]
+                sidecar::log_dev_command(app.handle());
+                if sidecar_lazy {
+                    tracing::info!("Sidecars start on first use");
+                    sidecar::defer_all(app.handle());
+                } else {
+                    if !headless {
+                        splash::show_splash(app.handle())?;
+                    }
+                    // Spawn the sidecar processes. A failure leaves the window
+                    // up so the splash can show the error and offer a retry.
+                    for descriptor in sidecar::descriptors(app.handle()) {
+                        sidecar::spawn_initial(app.handle(), descriptor);
                     }
-                });
+                }
+                sidecar::start_health_poller(app.handle());
+            }
+            if headless {
+                headless::start(app.handle());
+                return Ok(());
             }
+            updater::remove_stale_artifacts(app.handle());
+            updater::start_startup_check(app.handle());
+            updater::start_update_poller(app.handle());
+            tray::create_tray(app.handle())?;
+            deep_link::init(app.handle());
+            first_run::check(app.handle());
 
             Ok(())
         })
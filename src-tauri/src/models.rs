@@ -0,0 +1,167 @@
+// Local embedding models
+//
+// Each model is a folder under the models dir (`models_dir` in the sidecar
+// config, or the backend's own models folder in its data dir), either
+// directly or one level down as in `sentence-transformers/all-MiniLM-L6-v2`.
+// The selected one is saved as `model` in the config and handed to the
+// backend as FLASH_AI_MODEL_DIR.
+
+use crate::config;
+use crate::events::{self, AppEvent};
+use crate::lock::LockExt;
+use crate::sidecar::{self, SidecarConfig, SidecarEnvOverrides, DEFAULT_SIDECAR};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+pub const MODEL_DIR_ENV_VAR: &str = "FLASH_AI_MODEL_DIR";
+// The backend keeps its data here unless RETENTION_DATA_DIR says otherwise
+const DATA_DIR_ENV_VAR: &str = "RETENTION_DATA_DIR";
+const BACKEND_DATA_DIR: &str = "Retention";
+const MODELS_DIR: &str = "models";
+// A folder holding one of these is a model
+const MODEL_MARKERS: &[&str] = &["modules.json", "config.json"];
+
+#[derive(Clone, Serialize)]
+pub struct ModelEntry {
+    // Relative to the models dir, with `/` separators
+    name: String,
+    path: String,
+    selected: bool,
+}
+
+// Payload of `model-changed`
+#[derive(Clone, Serialize)]
+pub struct ModelChanged {
+    name: String,
+    path: String,
+}
+
+// Every model under the models dir, sorted by name. An empty list if the
+// dir doesn't exist yet.
+#[tauri::command]
+pub fn list_models(app: AppHandle) -> Result<Vec<ModelEntry>, String> {
+    let dir = models_dir(&app)?;
+    let selected = app.state::<Mutex<SidecarConfig>>().lock_or_recover().model.clone();
+    let mut models: Vec<ModelEntry> = find_models(&dir)
+        .into_iter()
+        .map(|(name, path)| ModelEntry {
+            selected: selected.as_deref() == Some(name.as_str()),
+            path: path.display().to_string(),
+            name,
+        })
+        .collect();
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(models)
+}
+
+// Switch the backend to another model: save the choice, then restart the
+// backend with FLASH_AI_MODEL_DIR pointing at it. Returns the new port.
+#[tauri::command]
+pub async fn select_model(app: AppHandle, name: String) -> Result<u16, String> {
+    let dir = models_dir(&app)?;
+    let path = find_models(&dir)
+        .into_iter()
+        .find(|(model, _)| *model == name)
+        .map(|(_, path)| path)
+        .ok_or_else(|| format!("Unknown model: {}", name))?;
+    ensure_readable(&path)?;
+
+    app.state::<Mutex<SidecarConfig>>().lock_or_recover().model = Some(name.clone());
+    config::persist(&app)?;
+
+    // Keep any other overrides the backend was restarted with
+    let mut env = app
+        .state::<SidecarEnvOverrides>()
+        .0
+        .lock_or_recover()
+        .get(DEFAULT_SIDECAR)
+        .cloned()
+        .unwrap_or_default();
+    env.insert(MODEL_DIR_ENV_VAR.to_string(), path.display().to_string());
+    let port = sidecar::restart_sidecar_with_env(app.clone(), env, None).await?;
+
+    tracing::info!(model = %name, "Switched model");
+    events::emit(&app, AppEvent::ModelChanged(ModelChanged {
+        name,
+        path: path.display().to_string(),
+    }));
+    Ok(port)
+}
+
+// The saved model's folder, for the backend's environment at spawn. None
+// when nothing was picked or it has since been removed.
+pub fn selected_model_dir(app: &AppHandle) -> Option<PathBuf> {
+    let name = app.state::<Mutex<SidecarConfig>>().lock_or_recover().model.clone()?;
+    let path = models_dir(app).ok()?.join(&name);
+    if path.is_dir() {
+        Some(path)
+    } else {
+        tracing::warn!(model = %name, "Selected model is missing, using the default");
+        None
+    }
+}
+
+fn models_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    if let Some(dir) = app.state::<Mutex<SidecarConfig>>().lock_or_recover().models_dir.clone() {
+        return Ok(dir);
+    }
+    backend_data_dir(app).map(|dir| dir.join(MODELS_DIR))
+}
+
+// Where the backend keeps its database and the models it downloads
+pub fn backend_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    if let Some(dir) = std::env::var_os(DATA_DIR_ENV_VAR).filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    app.path()
+        .local_data_dir()
+        .map(|dir| dir.join(BACKEND_DATA_DIR))
+        .map_err(|e| format!("Failed to resolve the backend's data dir: {}", e))
+}
+
+// (name, path) of each model folder, up to two levels down
+fn find_models(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut models = Vec::new();
+    for (name, path) in subdirs(dir) {
+        if is_model(&path) {
+            models.push((name, path));
+            continue;
+        }
+        for (inner, inner_path) in subdirs(&path) {
+            if is_model(&inner_path) {
+                models.push((format!("{}/{}", name, inner), inner_path));
+            }
+        }
+    }
+    models
+}
+
+// Non-hidden subdirectories, by name
+fn subdirs(dir: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| Some((entry.file_name().into_string().ok()?, entry.path())))
+        .filter(|(name, _)| !name.starts_with('.'))
+        .collect()
+}
+
+fn is_model(dir: &Path) -> bool {
+    MODEL_MARKERS.iter().any(|marker| dir.join(marker).is_file())
+}
+
+fn ensure_readable(dir: &Path) -> Result<(), String> {
+    std::fs::read_dir(dir).map_err(|e| format!("Can't read {}: {}", dir.display(), e))?;
+    for marker in MODEL_MARKERS {
+        let path = dir.join(marker);
+        if path.is_file() {
+            std::fs::File::open(&path).map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
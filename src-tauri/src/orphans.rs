@@ -0,0 +1,131 @@
+// Keeping sidecars from outliving the app
+//
+// A graceful exit stops every sidecar, but if the app is force-killed (or
+// crashes hard enough that the panic hook never runs) the children are
+// left behind still holding their ports. Each platform has its own way
+// of tying them to us:
+//
+// - Windows: every sidecar is assigned to a Job Object created with
+//   kill-on-close. The OS closes our handle to it when we die, however
+//   that happens, which terminates everything in the job, including
+//   processes the sidecar started itself.
+// - Unix: the sidecar puts itself in its own process group when it sees
+//   FLASH_AI_PARENT_PID, announces its pid as the group id, and kills the
+//   group once that parent is gone. Here we kill the whole group whenever
+//   we kill a sidecar, so nothing it spawned is left running either.
+
+use tauri::AppHandle;
+#[cfg(windows)]
+use tauri::Manager;
+
+// Our pid, passed to every sidecar so it can notice when we're gone
+pub const PARENT_PID_ENV_VAR: &str = "FLASH_AI_PARENT_PID";
+
+#[cfg(windows)]
+pub struct SidecarJob(job::Job);
+
+// Called once during setup, before any sidecar is spawned
+pub fn init(app: &AppHandle) {
+    #[cfg(windows)]
+    match job::Job::kill_on_close() {
+        Ok(job) => {
+            app.manage(SidecarJob(job));
+        }
+        Err(e) => tracing::warn!("Failed to create job object for sidecars: {}", e),
+    }
+    #[cfg(not(windows))]
+    let _ = app;
+}
+
+// Called right after a sidecar is spawned
+pub fn adopt(app: &AppHandle, pid: u32) {
+    #[cfg(windows)]
+    if let Some(job) = app.try_state::<SidecarJob>() {
+        if let Err(e) = job.0.assign(pid) {
+            tracing::warn!(pid, "Failed to add sidecar to job object: {}", e);
+        }
+    }
+    #[cfg(not(windows))]
+    let _ = (app, pid);
+}
+
+// Kill the process group led by `pgid`, the pid the sidecar announced.
+// A group that's already gone is fine.
+pub fn kill_group(pgid: u32) {
+    #[cfg(unix)]
+    {
+        let Ok(pgid) = libc::pid_t::try_from(pgid) else {
+            return;
+        };
+        // killpg(1, ..) would signal init's group; never go near that
+        if pgid <= 1 {
+            return;
+        }
+        if unsafe { libc::killpg(pgid, libc::SIGKILL) } != 0 {
+            let e = std::io::Error::last_os_error();
+            if e.raw_os_error() != Some(libc::ESRCH) {
+                tracing::warn!(pgid, "Failed to kill sidecar process group: {}", e);
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = pgid;
+}
+
+#[cfg(windows)]
+mod job {
+    use std::ffi::c_void;
+    use std::io;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation, SetInformationJobObject,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+    use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE};
+
+    // Never closed: the handle has to live exactly as long as the process,
+    // and the OS closes it for us at exit
+    pub struct Job(HANDLE);
+
+    // A job handle can be used from any thread
+    unsafe impl Send for Job {}
+    unsafe impl Sync for Job {}
+
+    impl Job {
+        pub fn kill_on_close() -> io::Result<Self> {
+            unsafe {
+                let handle = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+                if handle.is_null() {
+                    return Err(io::Error::last_os_error());
+                }
+                let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+                info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+                let ok = SetInformationJobObject(
+                    handle,
+                    JobObjectExtendedLimitInformation,
+                    &info as *const _ as *const c_void,
+                    std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                );
+                if ok == 0 {
+                    let e = io::Error::last_os_error();
+                    CloseHandle(handle);
+                    return Err(e);
+                }
+                Ok(Job(handle))
+            }
+        }
+
+        pub fn assign(&self, pid: u32) -> io::Result<()> {
+            unsafe {
+                let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid);
+                if process.is_null() {
+                    return Err(io::Error::last_os_error());
+                }
+                let ok = AssignProcessToJobObject(self.0, process);
+                let result = if ok == 0 { Err(io::Error::last_os_error()) } else { Ok(()) };
+                CloseHandle(process);
+                result
+            }
+        }
+    }
+}
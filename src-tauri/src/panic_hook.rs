@@ -0,0 +1,51 @@
+// Panic reporting
+//
+// The windowed release build has no console, so a panic would close the app
+// without a trace. The hook logs the panic with a backtrace, kills the
+// sidecars so they aren't orphaned and, where it can, shows an error dialog
+// before the default hook runs. Then it exits: the async runtime catches
+// panics in commands and tasks, which would otherwise leave the app running
+// with its backend killed.
+
+use crate::sidecar;
+use std::backtrace::Backtrace;
+use std::sync::OnceLock;
+use std::thread::ThreadId;
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+static MAIN_THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+// Call first thing in main, on the main thread
+pub fn install() {
+    let _ = MAIN_THREAD.set(std::thread::current().id());
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let thread = std::thread::current();
+        let thread_name = thread.name().unwrap_or("<unnamed>");
+        tracing::error!(thread = thread_name, "Panic: {}\n{}", info, Backtrace::force_capture());
+
+        if let Some(app) = APP.get() {
+            sidecar::kill_all_on_panic(app);
+            let dialog = app
+                .dialog()
+                .message(format!("Retention ran into an unexpected error and has to close.\n\n{}", info))
+                .title("Retention")
+                .kind(MessageDialogKind::Error);
+            // A blocking dialog would deadlock the event loop it runs on
+            if MAIN_THREAD.get() == Some(&thread.id()) {
+                dialog.show(|_| {});
+            } else {
+                dialog.blocking_show();
+            }
+        }
+        default_hook(info);
+        std::process::exit(1);
+    }));
+}
+
+// Lets the hook reach the sidecars and the dialog plugin; call from setup
+pub fn set_app(app: &AppHandle) {
+    let _ = APP.set(app.clone());
+}
@@ -0,0 +1,56 @@
+// Safe mode
+//
+// A way back in when a bad config or a wedged sidecar keeps the app from
+// working. restart_in_safe_mode leaves a marker file in the app config dir
+// and restarts. The next launch finds the marker and deletes it straight
+// away, so only that one launch is affected: it uses the default config and
+// doesn't spawn sidecars up front (they still start on first use), leaving
+// the UI free to fix the settings or export diagnostics.
+
+use std::sync::atomic::Ordering;
+use tauri::{AppHandle, Manager, State};
+
+const MARKER_FILE: &str = "safe-mode";
+
+// Whether this launch is in safe mode
+pub struct SafeMode(pub bool);
+
+// Called first thing in setup; true if the previous run asked for safe mode
+pub fn take_marker(app: &AppHandle) -> bool {
+    let Ok(path) = app.path().app_config_dir().map(|dir| dir.join(MARKER_FILE)) else {
+        return false;
+    };
+    if !path.exists() {
+        return false;
+    }
+    if let Err(e) = std::fs::remove_file(&path) {
+        // Better to be stuck in safe mode than to lose the way into it
+        tracing::warn!("Failed to remove {}: {}", path.display(), e);
+    }
+    true
+}
+
+#[tauri::command]
+pub fn is_safe_mode(safe_mode: State<SafeMode>) -> bool {
+    safe_mode.0
+}
+
+// Shut down as usual and relaunch in safe mode. Only returns on error.
+#[tauri::command]
+pub async fn restart_in_safe_mode(app: AppHandle) -> Result<(), String> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve the app config dir: {}", e))?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(MARKER_FILE);
+    std::fs::write(&path, b"").map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    if app.state::<crate::Quitting>().0.swap(true, Ordering::SeqCst) {
+        return Err("The app is already quitting".to_string());
+    }
+    tracing::info!("Restarting in safe mode");
+    crate::shutdown(&app).await;
+    crate::sidecar::close_log_files(&app);
+    app.restart();
+}
@@ -0,0 +1,90 @@
+// Environment self-test
+//
+// The first thing support asks users to run when something's wrong: quick
+// checks of the basics (sidecar binary and resources, log dir, ports,
+// update server) before anyone looks at the backend itself. Nothing here
+// changes state; ports are bound and released straight away and the log
+// dir probe file is removed again.
+
+use crate::sidecar::{self, SidecarAddr, SidecarDescriptor};
+use crate::updater::{self, UpdateError};
+use serde::Serialize;
+use std::net::{SocketAddr, TcpListener};
+use tauri::{AppHandle, Manager};
+
+#[derive(Serialize)]
+pub struct CheckResult {
+    name: String,
+    passed: bool,
+    detail: String,
+}
+
+impl CheckResult {
+    fn new(name: impl Into<String>, result: Result<String, String>) -> Self {
+        let (passed, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(detail) => (false, detail),
+        };
+        Self { name: name.into(), passed, detail }
+    }
+}
+
+// Run every check, in the order the UI lists them
+#[tauri::command]
+pub async fn run_self_test(app: AppHandle) -> Vec<CheckResult> {
+    let mut results = Vec::new();
+    for descriptor in sidecar::descriptors(&app) {
+        let program = sidecar::locate_program(&descriptor).map(|path| path.display().to_string());
+        results.push(CheckResult::new(format!("{} sidecar binary", descriptor.name), program));
+        #[cfg(not(debug_assertions))]
+        results.push(CheckResult::new(
+            format!("{} sidecar resources", descriptor.name),
+            sidecar::check_resources(&app, &descriptor).map(|dir| dir.display().to_string()),
+        ));
+        results.push(CheckResult::new(format!("{} sidecar port", descriptor.name), check_port(&app, &descriptor)));
+    }
+    results.push(CheckResult::new("Log directory writable", check_log_dir(&app)));
+    results.push(CheckResult::new("Update server reachable", check_update_server(&app).await));
+
+    let failed = results.iter().filter(|result| !result.passed).count();
+    tracing::info!(checks = results.len(), failed, "Ran self-test");
+    results
+}
+
+// A fixed port must be free, unless our own sidecar is the one holding it.
+// Without one, binding any port on the host is enough.
+fn check_port(app: &AppHandle, descriptor: &SidecarDescriptor) -> Result<String, String> {
+    let host = descriptor.host();
+    let Some(port) = descriptor.port else {
+        return match TcpListener::bind((host, 0)) {
+            Ok(_) => Ok(format!("No fixed port; {} accepts connections", host)),
+            Err(e) => Err(format!("Could not bind a port on {}: {}", host, e)),
+        };
+    };
+    let addr = SocketAddr::new(host, port);
+    if app.state::<SidecarAddr>().0.borrow().get(&descriptor.name) == Some(&addr) {
+        return Ok(format!("{} is in use by the running sidecar", addr));
+    }
+    match TcpListener::bind(addr) {
+        Ok(_) => Ok(format!("{} is free", addr)),
+        Err(e) => Err(format!("{} is not available: {}", addr, e)),
+    }
+}
+
+fn check_log_dir(app: &AppHandle) -> Result<String, String> {
+    let dir = app.path().app_log_dir().map_err(|e| format!("Failed to resolve the app log dir: {}", e))?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let probe = dir.join(".self-test");
+    std::fs::write(&probe, b"ok").map_err(|e| format!("Failed to write to {}: {}", dir.display(), e))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(dir.display().to_string())
+}
+
+// Disabled updates aren't a failure; there's just nothing to reach
+async fn check_update_server(app: &AppHandle) -> Result<String, String> {
+    match updater::check_update_connectivity(app.clone()).await {
+        Ok(()) => Ok(updater::get_update_endpoint(app.clone()).unwrap_or_default()),
+        Err(UpdateError::UpdaterDisabled) => Ok("Updates are disabled; skipped".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
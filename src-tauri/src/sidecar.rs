@@ -0,0 +1,2641 @@
+// Sidecar process management
+//
+// Spawns the Python FastAPI backend (and any extra sidecars listed in the
+// config), watches their output for the port they bind, restarts them if
+// they crash and tears them down when the app closes.
+//
+// Every sidecar has a name; commands that take an optional `name` act on
+// the default "backend" sidecar when it's omitted.
+
+use crate::config;
+use crate::events::{self, AppEvent};
+use crate::lock::LockExt;
+use crate::rotating_log::RotatingLog;
+use crate::startup_metrics::{self, StartupMetrics};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::Duration;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_opener::OpenerExt;
+use tauri_plugin_shell::process::{Command, CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+use tokio::sync::{oneshot, watch, Notify};
+use tokio::time::Instant;
+use tracing::{error, info, info_span, warn, Instrument};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", content = "detail", rename_all = "camelCase")]
+pub enum SidecarStatus {
+    Starting,
+    Running(u16),
+    Crashed { code: Option<i32> },
+    Stopped,
+}
+
+// All of the per-sidecar state below is keyed by sidecar name
+pub struct SidecarStatusState(pub Arc<Mutex<HashMap<String, SidecarStatus>>>);
+// Woken whenever any sidecar's status changes
+pub struct SidecarStatusChanged(pub Notify);
+// Known addresses; subscribe to be woken when any of them changes
+pub struct SidecarAddr(pub watch::Sender<HashMap<String, SocketAddr>>);
+// The port each sidecar last reported ready on; unlike SidecarAddr it's
+// kept while the sidecar is down, to tell whether a restart moved it
+pub struct SidecarLastPort(pub Mutex<HashMap<String, u16>>);
+
+impl SidecarLastPort {
+    // Record the port `name` is now serving on; the previous one if it moved
+    fn moved(&self, name: &str, port: u16) -> Option<u16> {
+        let old_port = self.0.lock_or_recover().insert(name.to_string(), port);
+        old_port.filter(|&old_port| old_port != port)
+    }
+}
+pub struct SidecarChild(pub Arc<Mutex<HashMap<String, CommandChild>>>);
+// Most recent output lines from both streams, oldest first, each prefixed
+// with the stream it came from
+pub struct SidecarLogs(pub Arc<Mutex<HashMap<String, VecDeque<String>>>>);
+// Log files in the app log dir, opened on first write
+pub struct SidecarLogFile(pub Mutex<HashMap<String, RotatingLog>>);
+// Sequence number of the last sidecar-log event, shared by all sidecars
+pub struct SidecarLogSeq(pub AtomicU64);
+// Sidecars we killed on purpose, so their exit isn't treated as a crash
+pub struct SidecarStopping(pub Mutex<HashSet<String>>);
+// Values the sidecar announced on stdout (`SIDECAR_PID=`, `SIDECAR_MODEL=`,
+// ...), keyed by the lower-cased key without the prefix. Reset on spawn.
+pub struct SidecarInfo(pub Mutex<HashMap<String, HashMap<String, String>>>);
+// When each sidecar crashed within the restart window, oldest first
+pub struct SidecarCrashes(pub Mutex<HashMap<String, VecDeque<Instant>>>);
+// What each sidecar was last spawned with
+pub struct SidecarCommandLine(pub Mutex<HashMap<String, SidecarCommandInfo>>);
+// Sidecars with a spawn in flight; a second spawn for the same one is
+// refused until the first has reported its port or failed
+pub struct SidecarSpawning(pub Arc<Mutex<HashSet<String>>>);
+// Sidecars held back by `lazy_sidecar` that nothing has needed yet
+pub struct SidecarLazy(pub Mutex<HashSet<String>>);
+
+impl SidecarLazy {
+    // Whether `name` was still waiting for first use. It leaves the set
+    // under the lock, so of several concurrent callers exactly one gets true.
+    fn take(&self, name: &str) -> bool {
+        self.0.lock_or_recover().remove(name)
+    }
+}
+// Set for the whole run when sidecars are turned off (FLASH_AI_NO_SIDECAR
+// or `disable_sidecar`); nothing is spawned and commands that need a
+// sidecar fail right away
+pub struct SidecarDisabled(pub bool);
+
+pub const DEFAULT_SIDECAR: &str = "backend";
+
+// How the app learns which port a sidecar listens on
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortMode {
+    // The sidecar picks a port and prints SIDECAR_PORT=
+    #[default]
+    Announce,
+    // We reserve a free port and pass it as `--port <n>`. The sidecar still
+    // prints SIDECAR_PORT= once it's ready, but we don't depend on it to
+    // know where to connect.
+    Assign,
+}
+
+// Verbosity of the built-in backend, passed as `--log-level`
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SidecarLogLevel {
+    Trace,
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl SidecarLogLevel {
+    const ALL: [SidecarLogLevel; 5] = [Self::Trace, Self::Debug, Self::Info, Self::Warn, Self::Error];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl std::str::FromStr for SidecarLogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|level| level.as_str() == s).ok_or_else(|| {
+            let allowed: Vec<&str> = Self::ALL.iter().map(|level| level.as_str()).collect();
+            format!("Invalid log level: {} (expected one of {})", s, allowed.join(", "))
+        })
+    }
+}
+
+// Where the built-in backend runs its model, passed as `--device`. With
+// auto it uses a GPU when one is usable.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SidecarDevice {
+    #[default]
+    Auto,
+    Cpu,
+    Cuda,
+}
+
+impl SidecarDevice {
+    const ALL: [SidecarDevice; 3] = [Self::Auto, Self::Cpu, Self::Cuda];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Cpu => "cpu",
+            Self::Cuda => "cuda",
+        }
+    }
+}
+
+impl std::str::FromStr for SidecarDevice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|device| device.as_str() == s).ok_or_else(|| {
+            let allowed: Vec<&str> = Self::ALL.iter().map(|device| device.as_str()).collect();
+            format!("Invalid device: {} (expected one of {})", s, allowed.join(", "))
+        })
+    }
+}
+
+// Shared secret the sidecar requires on every request, so other local
+// processes can't use its port. Generated per launch and only ever held in
+// memory.
+pub struct SidecarToken(pub String);
+
+impl SidecarToken {
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        getrandom::fill(&mut bytes).expect("Failed to generate sidecar auth token");
+        Self(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+// How to launch one sidecar
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SidecarDescriptor {
+    pub name: String,
+    // Program and arguments used in dev builds
+    pub dev_program: String,
+    #[serde(default)]
+    pub dev_args: Vec<String>,
+    // Working directory for dev builds; the repo root when unset, so
+    // `-m python_sidecar` resolves wherever the app was launched from
+    #[serde(default)]
+    pub dev_cwd: Option<PathBuf>,
+    // Bundled binary (listed under externalBin in tauri.conf.json) and
+    // arguments used in release builds
+    pub binary: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub port_mode: PortMode,
+    // Port to pass in Assign mode; an ephemeral one is used when it's unset
+    // or already taken
+    #[serde(default)]
+    pub port: Option<u16>,
+    // Interface to bind, passed as `--host`; 127.0.0.1 when unset
+    #[serde(default)]
+    pub host: Option<IpAddr>,
+    // The sidecar prints SIDECAR_READY=1 once it's serving, and only that
+    // marks it running. Otherwise announcing the address is enough.
+    #[serde(default)]
+    pub ready_signal: bool,
+    // Files or folders under the resource dir the release binary needs,
+    // e.g. a config or tokenizer listed under bundle > resources. Checked
+    // before every spawn.
+    #[serde(default)]
+    pub resources: Vec<PathBuf>,
+}
+
+impl SidecarDescriptor {
+    fn backend() -> Self {
+        Self {
+            name: DEFAULT_SIDECAR.to_string(),
+            dev_program: "python".to_string(),
+            dev_args: vec!["-m".to_string(), "python_sidecar".to_string()],
+            dev_cwd: None,
+            binary: SIDECAR_BINARY.to_string(),
+            args: Vec::new(),
+            port_mode: PortMode::Assign,
+            port: None,
+            host: None,
+            ready_signal: true,
+            resources: BACKEND_RESOURCES.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    pub fn host(&self) -> IpAddr {
+        self.host.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    }
+
+    // Announcements are `SIDECAR_<KEY>=<value>` lines, e.g.
+    // `SIDECAR_PORT=8000`. Any key can be written `SIDECAR_<KEY>[ocr]=...`
+    // when a sidecar wants to name itself; lines naming another sidecar are
+    // skipped. Returns the key without its prefix.
+    fn announcement<'a>(&self, line: &'a str) -> Option<(&'a str, &'a str)> {
+        let rest = line.strip_prefix(ANNOUNCEMENT_PREFIX)?;
+        let (key, rest) = rest.split_at(rest.find(['=', '['])?);
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_') {
+            return None;
+        }
+        let value = rest.strip_prefix('=').or_else(|| {
+            rest.strip_prefix('[')
+                .and_then(|rest| rest.strip_prefix(self.name.as_str()))
+                .and_then(|rest| rest.strip_prefix("]="))
+        })?;
+        Some((key, value.trim()))
+    }
+
+    // PORT is on our host; ADDR (e.g. `[::1]:8000`) is for a sidecar bound
+    // elsewhere
+    fn parse_addr(&self, key: &str, value: &str) -> Option<SocketAddr> {
+        match key {
+            "ADDR" => value.parse().ok(),
+            "PORT" => Some(SocketAddr::new(self.host(), value.parse().ok()?)),
+            _ => None,
+        }
+    }
+
+    fn log_file_name(&self) -> String {
+        if self.name == DEFAULT_SIDECAR {
+            SIDECAR_LOG_FILE.to_string()
+        } else {
+            format!("sidecar-{}.log", self.name)
+        }
+    }
+}
+
+// Tunables for the sidecar: the `sidecar` section of config.json in the
+// app config dir, or sidecar-config.json next to the executable until
+// config.json exists (see config.rs)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SidecarConfig {
+    // How long a freshly spawned sidecar gets to print SIDECAR_PORT=
+    pub startup_timeout_secs: u64,
+    // Seconds between background health checks; 0 turns them off
+    pub health_check_interval_secs: u64,
+    // How the built-in backend gets its port; extra sidecars set their own
+    pub port_mode: PortMode,
+    // Fixed port for the built-in backend in Assign mode
+    pub port: Option<u16>,
+    // Interface for the built-in backend to bind, e.g. "::1"
+    pub host: Option<IpAddr>,
+    // Python interpreter for the built-in backend in dev builds, e.g.
+    // "python3" or a virtualenv's python; FLASH_AI_PYTHON overrides it
+    pub python: Option<String>,
+    // Working directory for the built-in backend in dev builds
+    pub dev_cwd: Option<PathBuf>,
+    // Sidecars to run alongside the built-in backend
+    pub extra_sidecars: Vec<SidecarDescriptor>,
+    // tracing filter for the app's own log, e.g. "debug" or
+    // "retention=trace"; RUST_LOG overrides it
+    pub log_filter: Option<String>,
+    // Run the shell without any sidecar, e.g. for UI work or screenshots;
+    // FLASH_AI_NO_SIDECAR=1 does the same
+    pub disable_sidecar: bool,
+    // How long a crashed sidecar that's about to be restarted keeps showing
+    // as starting before it's reported as crashed; 0 reports it right away
+    pub crash_grace_ms: u64,
+    // Crash restarts allowed within `restart_window_secs`; one more crash
+    // and the sidecar is left crashed until it's started again by hand
+    pub max_restarts: u32,
+    // Crashes older than this no longer count toward `max_restarts`, so a
+    // sidecar that stays up this long starts over with a clean slate
+    pub restart_window_secs: u64,
+    // How long a sidecar asked to shut down gets to exit on its own before
+    // it's killed; 0 kills it right away
+    pub shutdown_grace_secs: u64,
+    // Don't spawn sidecars at launch; each starts the first time a command
+    // needs it (wait_for_sidecar, sidecar_request, ...)
+    pub lazy_sidecar: bool,
+    // Where list_models looks; the backend's own models folder when unset
+    pub models_dir: Option<PathBuf>,
+    // Model picked with select_model, relative to the models dir
+    pub model: Option<String>,
+    // The built-in backend's verbosity; set_sidecar_log_level changes it
+    pub log_level: SidecarLogLevel,
+    // Where the built-in backend runs its model; set_device changes it
+    pub device: SidecarDevice,
+    // Start the built-in backend with `--read-only`, for demos. The backend
+    // is what refuses changes; the UI only uses this (via get_runtime_flags)
+    // to disable its editing controls.
+    pub demo_mode: bool,
+    // Recent backend startup times, recorded by the app
+    pub startup_metrics: StartupMetrics,
+}
+
+impl Default for SidecarConfig {
+    fn default() -> Self {
+        Self {
+            startup_timeout_secs: DEFAULT_STARTUP_TIMEOUT_SECS,
+            health_check_interval_secs: DEFAULT_HEALTH_CHECK_INTERVAL_SECS,
+            port_mode: PortMode::Assign,
+            port: None,
+            host: None,
+            python: None,
+            dev_cwd: None,
+            extra_sidecars: Vec::new(),
+            log_filter: None,
+            disable_sidecar: false,
+            crash_grace_ms: DEFAULT_CRASH_GRACE_MS,
+            max_restarts: DEFAULT_MAX_RESTARTS,
+            restart_window_secs: DEFAULT_RESTART_WINDOW_SECS,
+            shutdown_grace_secs: DEFAULT_SHUTDOWN_GRACE_SECS,
+            lazy_sidecar: false,
+            models_dir: None,
+            model: None,
+            log_level: SidecarLogLevel::default(),
+            device: SidecarDevice::default(),
+            demo_mode: false,
+            startup_metrics: StartupMetrics::default(),
+        }
+    }
+}
+
+// Process table used to sample sidecar CPU and memory. Kept between calls
+// because CPU usage is measured relative to the previous refresh.
+pub struct SidecarSystem(pub Mutex<System>);
+
+#[derive(Clone, Serialize)]
+pub struct SidecarResources {
+    // Percent of a single core, so this can exceed 100 on multi-core machines
+    cpu_percent: f32,
+    memory_bytes: u64,
+}
+
+// Background health poller for the default sidecar, aborted when the app
+// closes
+pub struct HealthPoller(pub Mutex<Option<tauri::async_runtime::JoinHandle<()>>>);
+
+// Why health checks are paused; they run only while neither is set
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HealthPause {
+    // pause_health_checks was called
+    pub requested: bool,
+    // The main window is minimized, e.g. before the laptop goes to sleep
+    pub minimized: bool,
+}
+
+impl HealthPause {
+    fn active(&self) -> bool {
+        self.requested || self.minimized
+    }
+}
+
+pub struct HealthChecksPaused(pub watch::Sender<HealthPause>);
+
+const SIDECAR_CONFIG_FILE: &str = "sidecar-config.json";
+
+// Extra environment variables set on the sidecar process, on top of the
+// environment it inherits from the app
+pub struct SidecarEnv {
+    pub vars: HashMap<String, String>,
+}
+
+// Flags for the built-in backend from `--sidecar-arg KEY=VALUE` on our own
+// command line, already expanded to `--KEY VALUE`
+pub struct SidecarCliArgs(pub Vec<String>);
+
+// Per-sidecar overrides from restart_sidecar_with_env, merged over SidecarEnv
+// and kept for later restarts
+pub struct SidecarEnvOverrides(pub Mutex<HashMap<String, HashMap<String, String>>>);
+
+// Variables the sidecar reads that we pass along explicitly when set. These
+// are also the only ones restart_sidecar_with_env may override.
+const FORWARDED_ENV_VARS: &[&str] = &[
+    "FLASH_AI_MODEL_DIR",
+    "HF_HOME",
+    "HF_HUB_OFFLINE",
+    "TRANSFORMERS_CACHE",
+    "SENTENCE_TRANSFORMERS_HOME",
+];
+// What restart_sidecar_clearing_cache deletes, relative to the backend's
+// data dir: the Hugging Face cache entry the backend downloads its model
+// into (see MODEL_CACHE_DIR in scoring.py). Never the database, and never
+// the models added to the same folder by hand.
+const BACKEND_CACHE_DIRS: &[&str] = &[
+    "models/models--sentence-transformers--all-MiniLM-L6-v2",
+    "models/.locks/models--sentence-transformers--all-MiniLM-L6-v2",
+];
+// Sidecars announce themselves on stdout with SIDECAR_<KEY>= lines
+const ANNOUNCEMENT_PREFIX: &str = "SIDECAR_";
+const ANNOUNCED_KEYS: &[&str] = &["PORT", "ADDR", "PID", "MODEL", "DEVICE", "LOADING", "READY"];
+// Optional KEY=VALUE overrides shipped next to the bundled resources
+const SIDECAR_ENV_FILE: &str = "sidecar.env";
+// Carries the SidecarToken; never written to sidecar.env or the logs
+const AUTH_TOKEN_ENV_VAR: &str = "FLASH_AI_AUTH_TOKEN";
+// Bundled binary for the built-in backend, set by build.rs from
+// tauri.conf.json's externalBin or FLASH_AI_SIDECAR_BINARY
+const SIDECAR_BINARY: &str = env!("SIDECAR_BINARY");
+// The PyInstaller runtime the bundled backend loads its modules and model
+// from, shipped under bundle > resources
+const BACKEND_RESOURCES: &[&str] = &["binaries/_internal"];
+// Dev-mode interpreter override for the built-in backend
+const PYTHON_ENV_VAR: &str = "FLASH_AI_PYTHON";
+// Dev-mode command line for the built-in backend, e.g.
+// `poetry run python -m python_sidecar`; replaces program and args both
+const SIDECAR_CMD_ENV_VAR: &str = "FLASH_AI_SIDECAR_CMD";
+const NO_SIDECAR_ENV_VAR: &str = "FLASH_AI_NO_SIDECAR";
+const SIDECAR_ARG_FLAG: &str = "--sidecar-arg";
+// Flags the app passes itself; a forwarded copy would contradict them
+const RESERVED_SIDECAR_ARGS: &[&str] = &["host", "port", "log-level", "device", "read-only"];
+// Environment variables whose names contain one of these have their values
+// hidden from get_sidecar_command_info
+const SECRET_ENV_MARKERS: &[&str] = &["TOKEN", "SECRET", "PASSWORD", "KEY", "CREDENTIAL"];
+const REDACTED: &str = "<redacted>";
+pub const SIDECAR_DISABLED_ERROR: &str = "Sidecar is disabled";
+
+// Retries for the first spawn at launch, which can fail while the disk or
+// an antivirus scan is slow; the delay doubles from SPAWN_RETRY_DELAY
+const SPAWN_RETRY_ATTEMPTS: u32 = 3;
+const SPAWN_RETRY_DELAY: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_RESTARTS: u32 = 5;
+const DEFAULT_RESTART_WINDOW_SECS: u64 = 300;
+const MAX_RESTART_BACKOFF_SECS: u64 = 30;
+const DEFAULT_STARTUP_TIMEOUT_SECS: u64 = 30;
+// Stderr lines kept for the startup failure report
+const STARTUP_STDERR_LINES: usize = 50;
+// Output lines kept for get_sidecar_logs
+const SIDECAR_LOG_LINES: usize = 500;
+const DEFAULT_LOG_TAIL_LINES: usize = 200;
+const SIDECAR_LOG_FILE: &str = "sidecar.log";
+const SIDECAR_LOG_MAX_BYTES: u64 = 5 * 1024 * 1024;
+const SIDECAR_LOG_MAX_FILES: usize = 3;
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+const SHUTDOWN_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const DEFAULT_SHUTDOWN_GRACE_SECS: u64 = 3;
+const DEFAULT_CRASH_GRACE_MS: u64 = 2000;
+const DEFAULT_HEALTH_CHECK_INTERVAL_SECS: u64 = 10;
+// Consecutive failed health checks before sidecar-unhealthy is emitted
+const UNHEALTHY_THRESHOLD: u32 = 3;
+
+// Sent whenever a sidecar process ends. `intentional` is set when it was
+// stopped by the app (stop, restart, shutdown) rather than crashing.
+#[derive(Clone, Serialize)]
+pub struct SidecarExited {
+    name: String,
+    code: Option<i32>,
+    signal: Option<i32>,
+    intentional: bool,
+}
+
+// One line of output, sent as it arrives. `seq` goes up by one per event
+// across all sidecars, so a jump means the UI missed some.
+#[derive(Clone, Serialize)]
+pub struct SidecarLog {
+    name: String,
+    stream: String,
+    line: String,
+    timestamp: String,
+    seq: u64,
+}
+
+// Sent only for unexpected exits, alongside sidecar-exited
+#[derive(Clone, Serialize)]
+pub struct SidecarCrashed {
+    name: String,
+    code: Option<i32>,
+    signal: Option<i32>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SidecarSpawnRetry {
+    name: String,
+    attempt: u32,
+    max_attempts: u32,
+    delay_ms: u64,
+    error: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SidecarRestarting {
+    name: String,
+    attempt: u32,
+    delay_ms: u64,
+}
+
+// Sent when a sidecar has crashed more than `max_restarts` times within
+// `restart_window_secs` and won't be restarted again
+#[derive(Clone, Serialize)]
+pub struct SidecarGivingUp {
+    name: String,
+    crashes: u32,
+    window_secs: u64,
+}
+
+// Payload of `sidecar-loading`, from a `SIDECAR_LOADING=<percent>` line
+#[derive(Clone, Serialize)]
+pub struct SidecarLoading {
+    name: String,
+    percent: u8,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SidecarReady {
+    name: String,
+    port: u16,
+    startup_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SidecarHealth {
+    healthy: bool,
+    latency_ms: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SidecarUnhealthy {
+    consecutive_failures: u32,
+    error: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SidecarPortConflict {
+    name: String,
+    port: u16,
+}
+
+// Payload of `sidecar-device-fallback`: `requested` was set with set_device
+// but the backend found no usable GPU, so it's running on `device` and
+// that's what's saved now
+#[derive(Clone, Serialize)]
+pub struct SidecarDeviceFallback {
+    requested: SidecarDevice,
+    device: SidecarDevice,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SidecarPortChanged {
+    name: String,
+    old_port: u16,
+    port: u16,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SidecarStartupFailed {
+    name: String,
+    error: String,
+    elapsed_ms: u64,
+    stderr: Vec<String>,
+}
+
+#[tauri::command]
+pub fn get_sidecar_status(status: State<SidecarStatusState>, name: Option<String>) -> SidecarStatus {
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    status.0.lock_or_recover().get(name).cloned().unwrap_or(SidecarStatus::Stopped)
+}
+
+// None while the port isn't known. With `timeout_ms`, a sidecar that is
+// still starting gets that long to announce its port first, so a UI that
+// asks right at launch doesn't read a stale None.
+#[tauri::command]
+pub async fn get_sidecar_port(app: AppHandle, name: Option<String>, timeout_ms: Option<u64>) -> Option<u16> {
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    // Subscribe before checking so an address set in between isn't missed
+    let mut addrs = app.state::<SidecarAddr>().0.subscribe();
+    let port = addrs.borrow_and_update().get(name).map(SocketAddr::port);
+    let Some(ms) = timeout_ms.filter(|_| port.is_none()) else {
+        return port;
+    };
+    if app.state::<SidecarStatusState>().0.lock_or_recover().get(name) != Some(&SidecarStatus::Starting) {
+        return None;
+    }
+    wait_for_port(&mut addrs, name, Duration::from_millis(ms)).await
+}
+
+// Wait up to `timeout` for `name` to appear in the address channel. An
+// address set before `addrs` was subscribed is returned right away.
+async fn wait_for_port(
+    addrs: &mut watch::Receiver<HashMap<String, SocketAddr>>,
+    name: &str,
+    timeout: Duration,
+) -> Option<u16> {
+    let wait = addrs.wait_for(|addrs| addrs.contains_key(name));
+    match tokio::time::timeout(timeout, wait).await {
+        Ok(Ok(addrs)) => addrs.get(name).map(SocketAddr::port),
+        _ => None,
+    }
+}
+
+// Resolves with the port once the sidecar is running, for a single await
+// on startup instead of polling get_sidecar_port. Without `timeout_ms` it
+// waits indefinitely, across crash restarts.
+#[tauri::command]
+pub async fn wait_for_sidecar(app: AppHandle, name: Option<String>, timeout_ms: Option<u64>) -> Result<u16, String> {
+    ensure_enabled(&app)?;
+    let name = name.unwrap_or_else(|| DEFAULT_SIDECAR.to_string());
+    start_if_lazy(&app, &name)?;
+    let wait = async {
+        let changed = app.state::<SidecarStatusChanged>();
+        loop {
+            // Register before checking so a change in between isn't missed
+            let notified = changed.0.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if let Some(SidecarStatus::Running(port)) = app.state::<SidecarStatusState>().0.lock_or_recover().get(&name) {
+                return *port;
+            }
+            notified.await;
+        }
+    };
+    match timeout_ms {
+        Some(ms) => tokio::time::timeout(Duration::from_millis(ms), wait)
+            .await
+            .map_err(|_| format!("Sidecar {} was not ready within {}ms", name, ms)),
+        None => Ok(wait.await),
+    }
+}
+
+// Full `host:port`, with IPv6 hosts in brackets
+#[tauri::command]
+pub fn get_sidecar_addr(addr_state: State<SidecarAddr>, name: Option<String>) -> Option<String> {
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    addr_state.0.borrow().get(name).map(SocketAddr::to_string)
+}
+
+// Everything the sidecar announced about itself, e.g. `pid` and `model`
+#[tauri::command]
+pub fn get_sidecar_info(info: State<SidecarInfo>, name: Option<String>) -> HashMap<String, String> {
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    info.0.lock_or_recover().get(name).cloned().unwrap_or_default()
+}
+
+// The UI sends this as `Authorization: Bearer <token>` on every request
+#[tauri::command]
+pub fn get_sidecar_token(token: State<SidecarToken>) -> String {
+    token.0.clone()
+}
+
+#[tauri::command]
+pub fn get_sidecar_pid(child_state: State<SidecarChild>, name: Option<String>) -> Option<u32> {
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    child_state.0.lock_or_recover().get(name).map(|child| child.pid())
+}
+
+#[tauri::command]
+pub fn get_sidecar_logs(logs: State<SidecarLogs>, name: Option<String>, lines: Option<usize>) -> Vec<String> {
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    let logs = logs.0.lock_or_recover();
+    let Some(logs) = logs.get(name) else {
+        return Vec::new();
+    };
+    let lines = lines.unwrap_or(DEFAULT_LOG_TAIL_LINES).min(SIDECAR_LOG_LINES);
+    logs.iter().skip(logs.len().saturating_sub(lines)).cloned().collect()
+}
+
+// Open the app log dir, where the sidecar logs are written, in the OS file
+// manager. Returns the directory so the UI can also show it.
+#[tauri::command]
+pub fn reveal_sidecar_logs(app: AppHandle) -> Result<String, String> {
+    let dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve the app log dir: {}", e))?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let dir = dir.display().to_string();
+    app.opener()
+        .open_path(&dir, None::<&str>)
+        .map_err(|e| format!("Failed to open {}: {}", dir, e))?;
+    Ok(dir)
+}
+
+// CPU usage is the average since the previous call, so the first sample for
+// a process reads 0; poll this on an interval for meaningful numbers.
+#[tauri::command]
+pub fn get_sidecar_resources(
+    child_state: State<SidecarChild>,
+    system: State<SidecarSystem>,
+    name: Option<String>,
+) -> Result<SidecarResources, String> {
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    let pid = child_state
+        .0
+        .lock_or_recover()
+        .get(name)
+        .map(|child| Pid::from_u32(child.pid()))
+        .ok_or("Sidecar is not running")?;
+
+    let mut system = system.0.lock_or_recover();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_cpu().with_memory(),
+    );
+    let process = system.process(pid).ok_or("Sidecar process not found")?;
+    Ok(SidecarResources {
+        cpu_percent: process.cpu_usage(),
+        memory_bytes: process.memory(),
+    })
+}
+
+// Payload of get_sidecar_command_info
+#[derive(Clone, Serialize)]
+pub struct SidecarCommandInfo {
+    // Full path of the program when it can be found (the bundled binary, or
+    // the dev program on PATH), otherwise the name it was started by
+    program: String,
+    args: Vec<String>,
+    cwd: String,
+    // Variables set on top of the app's own environment, sorted, with
+    // secret values replaced by "<redacted>"
+    env: BTreeMap<String, String>,
+}
+
+// Exactly how a sidecar was last spawned, for support to compare against a
+// working install. Recorded even when the spawn failed.
+#[tauri::command]
+pub fn get_sidecar_command_info(
+    state: State<SidecarCommandLine>,
+    name: Option<String>,
+) -> Result<SidecarCommandInfo, String> {
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    state
+        .0
+        .lock_or_recover()
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("The '{}' sidecar hasn't been started yet", name))
+}
+
+// One entry of list_processes
+#[derive(Clone, Serialize)]
+pub struct SidecarProcess {
+    name: String,
+    pid: u32,
+    // starting, running, crashed or stopped as we track it; "gone" when the
+    // OS no longer has the process and "zombie" when it has exited but
+    // hasn't been reaped, both meaning our bookkeeping is out of date
+    status: &'static str,
+    port: Option<u16>,
+}
+
+// Every sidecar process we spawned and still hold on to, by name, checked
+// against the OS process table
+#[tauri::command]
+pub fn list_processes(app: AppHandle, system: State<SidecarSystem>) -> Vec<SidecarProcess> {
+    let mut pids: Vec<(String, u32)> = app
+        .state::<SidecarChild>()
+        .0
+        .lock_or_recover()
+        .iter()
+        .map(|(name, child)| (name.clone(), child.pid()))
+        .collect();
+    pids.sort();
+
+    let mut system = system.0.lock_or_recover();
+    let os_pids: Vec<Pid> = pids.iter().map(|&(_, pid)| Pid::from_u32(pid)).collect();
+    system.refresh_processes_specifics(ProcessesToUpdate::Some(&os_pids), true, ProcessRefreshKind::nothing());
+    let statuses = app.state::<SidecarStatusState>().0.lock_or_recover().clone();
+
+    pids.into_iter()
+        .map(|(name, pid)| {
+            let status = match system.process(Pid::from_u32(pid)).map(|process| process.status()) {
+                None => "gone",
+                Some(ProcessStatus::Zombie) => "zombie",
+                Some(_) => match statuses.get(&name) {
+                    Some(SidecarStatus::Starting) => "starting",
+                    Some(SidecarStatus::Running(_)) => "running",
+                    Some(SidecarStatus::Crashed { .. }) => "crashed",
+                    Some(SidecarStatus::Stopped) | None => "stopped",
+                },
+            };
+            if matches!(status, "gone" | "zombie") {
+                warn!(sidecar = %name, pid, status, "Tracked sidecar process is not alive");
+            }
+            SidecarProcess {
+                port: port_of(&app, &name),
+                name,
+                pid,
+                status,
+            }
+        })
+        .collect()
+}
+
+// `timeout_ms` defaults to the background checks' 2s
+#[tauri::command]
+pub async fn check_sidecar_health(
+    app: AppHandle,
+    addr_state: State<'_, SidecarAddr>,
+    token: State<'_, SidecarToken>,
+    name: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<u64, String> {
+    ensure_enabled(&app)?;
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    let addr = addr_state.0.borrow().get(name).copied().ok_or("Sidecar is not running")?;
+    let timeout = timeout_ms.filter(|&ms| ms > 0).map(Duration::from_millis).unwrap_or(HEALTH_CHECK_TIMEOUT);
+    ping_health(addr, &token.0, timeout).await
+}
+
+// GET /health on the sidecar, returning the round trip time in milliseconds
+async fn ping_health(addr: SocketAddr, token: &str, timeout: Duration) -> Result<u64, String> {
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let started = Instant::now();
+    let response = client
+        .get(format!("http://{}/health", addr))
+        .bearer_auth(token)
+        .send()
+        .await
+        .map_err(|e| {
+            if e.is_timeout() {
+                format!("Health check timed out after {}ms", timeout.as_millis())
+            } else {
+                format!("Health check failed: {}", e)
+            }
+        })?;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    if !response.status().is_success() {
+        return Err(format!("Health check returned {}", response.status()));
+    }
+    Ok(latency_ms)
+}
+
+// Ping the sidecar on the configured interval and report the results.
+// While the sidecar has no port (stopped or starting) the poller sleeps
+// until it gets one; while checks are paused it skips them.
+pub fn start_health_poller(app: &AppHandle) {
+    let interval_secs = app.state::<Mutex<SidecarConfig>>().lock_or_recover().health_check_interval_secs;
+    if interval_secs == 0 {
+        return;
+    }
+
+    let handle = {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+            let mut addrs = app.state::<SidecarAddr>().0.subscribe();
+            let mut paused = app.state::<HealthChecksPaused>().0.subscribe();
+            let mut failures = 0;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    Ok(()) = paused.changed() => {
+                        // Failures from before a pause (or during sleep) don't count
+                        failures = 0;
+                        if paused.borrow_and_update().active() {
+                            continue;
+                        }
+                        // Resumed: check right away rather than at the next tick
+                        interval.reset();
+                    }
+                }
+                if paused.borrow_and_update().active() {
+                    continue;
+                }
+                let Some(addr) = addr_of(&app, DEFAULT_SIDECAR) else {
+                    failures = 0;
+                    if addrs.wait_for(|addrs| addrs.contains_key(DEFAULT_SIDECAR)).await.is_err() {
+                        return;
+                    }
+                    // First check a full interval after the port shows up
+                    interval.reset();
+                    continue;
+                };
+
+                let token = app.state::<SidecarToken>().0.clone();
+                match ping_health(addr, &token, HEALTH_CHECK_TIMEOUT).await {
+                    Ok(latency_ms) => {
+                        failures = 0;
+                        events::emit(&app, AppEvent::SidecarHealth(SidecarHealth {
+                            healthy: true,
+                            latency_ms: Some(latency_ms),
+                        }));
+                    }
+                    Err(e) => {
+                        failures += 1;
+                        events::emit(&app, AppEvent::SidecarHealth(SidecarHealth {
+                            healthy: false,
+                            latency_ms: None,
+                        }));
+                        if failures == UNHEALTHY_THRESHOLD {
+                            warn!(failures, error = %e, "Sidecar failed several health checks in a row");
+                            events::emit(&app, AppEvent::SidecarUnhealthy(SidecarUnhealthy {
+                                consecutive_failures: failures,
+                                error: e,
+                            }));
+                        }
+                    }
+                }
+            }
+        })
+    };
+    *app.state::<HealthPoller>().0.lock_or_recover() = Some(handle);
+}
+
+// Stop the background health checks until resume_health_checks, e.g. during
+// a long operation that keeps the sidecar too busy to answer
+#[tauri::command]
+pub fn pause_health_checks(app: AppHandle) {
+    update_health_pause(&app, |pause| pause.requested = true);
+}
+
+// Resuming runs a check straight away
+#[tauri::command]
+pub fn resume_health_checks(app: AppHandle) {
+    update_health_pause(&app, |pause| pause.requested = false);
+}
+
+// Called as the main window is minimized and restored
+pub fn set_window_minimized(app: &AppHandle, minimized: bool) {
+    update_health_pause(app, |pause| pause.minimized = minimized);
+}
+
+// Emits health-checks-paused or health-checks-resumed when that changes
+fn update_health_pause(app: &AppHandle, update: impl FnOnce(&mut HealthPause)) {
+    let mut transition = None;
+    app.state::<HealthChecksPaused>().0.send_if_modified(|pause| {
+        let before = *pause;
+        update(pause);
+        if before.active() != pause.active() {
+            transition = Some(pause.active());
+        }
+        before != *pause
+    });
+    match transition {
+        Some(true) => {
+            info!("Health checks paused");
+            events::emit(app, AppEvent::HealthChecksPaused);
+        }
+        Some(false) => {
+            info!("Health checks resumed");
+            events::emit(app, AppEvent::HealthChecksResumed);
+        }
+        None => {}
+    }
+}
+
+pub fn stop_health_poller(app: &AppHandle) {
+    if let Some(handle) = app.state::<HealthPoller>().0.lock_or_recover().take() {
+        handle.abort();
+    }
+}
+
+#[tauri::command]
+pub fn set_startup_timeout(config: State<Mutex<SidecarConfig>>, secs: u64) -> Result<(), String> {
+    if secs == 0 {
+        return Err("Startup timeout must be at least 1 second".to_string());
+    }
+    config.lock_or_recover().startup_timeout_secs = secs;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn start_sidecar(app: AppHandle, name: Option<String>) -> Result<u16, String> {
+    ensure_enabled(&app)?;
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    if app.state::<SidecarChild>().0.lock_or_recover().contains_key(name) {
+        return port_of(&app, name).ok_or_else(|| "Sidecar is still starting".to_string());
+    }
+    launch_sidecar(&app, name).await
+}
+
+#[tauri::command]
+pub async fn stop_sidecar(app: AppHandle, name: Option<String>) {
+    shutdown_sidecar(&app, name.as_deref().unwrap_or(DEFAULT_SIDECAR)).await;
+}
+
+#[tauri::command]
+pub async fn restart_sidecar(app: AppHandle, name: Option<String>) -> Result<u16, String> {
+    ensure_enabled(&app)?;
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    launch_sidecar(&app, name).await
+}
+
+// Restart with some environment variables replaced, e.g. FLASH_AI_MODEL_DIR
+// to switch models. Only the variables in FORWARDED_ENV_VARS are accepted.
+#[tauri::command]
+pub async fn restart_sidecar_with_env(
+    app: AppHandle,
+    env: HashMap<String, String>,
+    name: Option<String>,
+) -> Result<u16, String> {
+    ensure_enabled(&app)?;
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    descriptor(&app, name)?;
+    if let Some(key) = env.keys().find(|key| !FORWARDED_ENV_VARS.contains(&key.as_str())) {
+        return Err(format!("{} can't be overridden", key));
+    }
+    app.state::<SidecarEnvOverrides>().0.lock_or_recover().insert(name.to_string(), env);
+    launch_sidecar(&app, name).await
+}
+
+// For a backend that keeps crashing on a corrupt download: stop it, delete
+// the model it downloaded and start it again, so it fetches a fresh copy.
+// Returns the paths deleted. Only paths inside the backend's data dir are
+// touched.
+#[tauri::command]
+pub async fn restart_sidecar_clearing_cache(app: AppHandle) -> Result<Vec<String>, String> {
+    ensure_enabled(&app)?;
+    let data_dir = crate::models::backend_data_dir(&app)?;
+    // Windows won't delete files the backend still has open
+    shutdown_sidecar(&app, DEFAULT_SIDECAR).await;
+
+    let mut cleared = Vec::new();
+    for dir in BACKEND_CACHE_DIRS {
+        let path = data_dir.join(dir);
+        if !path.exists() {
+            continue;
+        }
+        ensure_inside(&data_dir, &path)?;
+        std::fs::remove_dir_all(&path).map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+        info!(path = %path.display(), "Cleared sidecar cache");
+        cleared.push(path.display().to_string());
+    }
+
+    launch_sidecar(&app, DEFAULT_SIDECAR).await?;
+    Ok(cleared)
+}
+
+// Refuse to delete `path` unless it resolves to somewhere strictly inside
+// `root`, so a symlink or a `..` can't point the deletion elsewhere
+fn ensure_inside(root: &Path, path: &Path) -> Result<(), String> {
+    let outside = || format!("Refusing to delete {}: it's outside {}", path.display(), root.display());
+    let root = root.canonicalize().map_err(|e| format!("Failed to resolve {}: {}", root.display(), e))?;
+    if std::fs::symlink_metadata(path).map_err(|e| e.to_string())?.file_type().is_symlink() {
+        return Err(outside());
+    }
+    let resolved = path.canonicalize().map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))?;
+    if resolved == root || !resolved.starts_with(&root) {
+        return Err(outside());
+    }
+    Ok(())
+}
+
+// Save a new `log_level` for the built-in backend and restart it at that
+// level. Takes one of trace, debug, info, warn or error; returns the new port.
+#[tauri::command]
+pub async fn set_sidecar_log_level(app: AppHandle, level: String) -> Result<u16, String> {
+    ensure_enabled(&app)?;
+    let level: SidecarLogLevel = level.parse()?;
+    app.state::<Mutex<SidecarConfig>>().lock_or_recover().log_level = level;
+    crate::config::persist(&app)?;
+    info!(level = level.as_str(), "Changed sidecar log level");
+    launch_sidecar(&app, DEFAULT_SIDECAR).await
+}
+
+// Save where the built-in backend runs its model and restart it there.
+// Takes cpu, cuda or auto; returns the new port. The backend reports the
+// device it actually picked with `SIDECAR_DEVICE=`, so if cuda finds no
+// usable GPU it's already on the CPU; cpu is saved in its place and
+// sidecar-device-fallback is sent.
+#[tauri::command]
+pub async fn set_device(app: AppHandle, device: String) -> Result<u16, String> {
+    ensure_enabled(&app)?;
+    let device: SidecarDevice = device.parse()?;
+    app.state::<Mutex<SidecarConfig>>().lock_or_recover().device = device;
+    crate::config::persist(&app)?;
+    info!(device = device.as_str(), "Changed sidecar device");
+    let port = launch_sidecar(&app, DEFAULT_SIDECAR).await?;
+
+    // Older backends don't report a device; trust those
+    let reported = app
+        .state::<SidecarInfo>()
+        .0
+        .lock_or_recover()
+        .get(DEFAULT_SIDECAR)
+        .and_then(|info| info.get("device").cloned());
+    if device == SidecarDevice::Cuda && reported.is_some_and(|reported| reported != device.as_str()) {
+        warn!("No usable GPU, the sidecar fell back to the CPU");
+        app.state::<Mutex<SidecarConfig>>().lock_or_recover().device = SidecarDevice::Cpu;
+        crate::config::persist(&app)?;
+        events::emit(&app, AppEvent::SidecarDeviceFallback(SidecarDeviceFallback {
+            requested: device,
+            device: SidecarDevice::Cpu,
+        }));
+    }
+    Ok(port)
+}
+
+// Turn demo mode on or off and restart the built-in backend so it takes
+// effect. Returns the new port.
+#[tauri::command]
+pub async fn set_demo_mode(app: AppHandle, enabled: bool) -> Result<u16, String> {
+    ensure_enabled(&app)?;
+    app.state::<Mutex<SidecarConfig>>().lock_or_recover().demo_mode = enabled;
+    crate::config::persist(&app)?;
+    info!(enabled, "Changed demo mode");
+    launch_sidecar(&app, DEFAULT_SIDECAR).await
+}
+
+// Restart the built-in backend in the background if it's running, for
+// settings that only apply at spawn. One that isn't running picks them up
+// whenever it starts.
+pub fn restart_backend_if_running(app: &AppHandle) {
+    if is_disabled(app) || !app.state::<SidecarChild>().0.lock_or_recover().contains_key(DEFAULT_SIDECAR) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = launch_sidecar(&app, DEFAULT_SIDECAR).await {
+            error!("Failed to restart sidecar: {}", e);
+        }
+    });
+}
+
+// Respawn on a fresh port picked by us, for when something else has taken
+// the current one (e.g. after sleep/resume). Like any restart onto a new
+// port, this emits sidecar-port-changed so open connections can reconnect.
+// Later restarts go back to the configured port.
+#[tauri::command]
+pub async fn move_sidecar_to_new_port(app: AppHandle, name: Option<String>) -> Result<u16, String> {
+    ensure_enabled(&app)?;
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    let mut descriptor = descriptor(&app, name)?;
+    let old_port = port_of(&app, name);
+    let port = reserve_port(descriptor.host()).map_err(|e| format!("Failed to pick a new port: {}", e))?;
+    descriptor.port_mode = PortMode::Assign;
+    descriptor.port = Some(port);
+
+    let port = launch(&app, &descriptor)
+        .await
+        .map_err(|e| format!("Sidecar failed to start on port {}: {}", port, e))?;
+    info!(sidecar = name, ?old_port, port, "Moved sidecar to a new port");
+    Ok(port)
+}
+
+// Replace any running process with a fresh sidecar and wait until it
+// reports its port. Fails if another launch of the same sidecar is still
+// waiting for its port.
+async fn launch_sidecar(app: &AppHandle, name: &str) -> Result<u16, String> {
+    launch(app, &descriptor(app, name)?).await
+}
+
+async fn launch(app: &AppHandle, descriptor: &SidecarDescriptor) -> Result<u16, String> {
+    let name = descriptor.name.as_str();
+    let _spawning = SpawnGuard::acquire(app, name)?;
+    // Started explicitly, so it's no longer waiting for first use
+    app.state::<SidecarLazy>().0.lock_or_recover().remove(name);
+    kill_sidecar(app, name);
+    app.state::<SidecarStopping>().0.lock_or_recover().remove(name);
+    // Started on purpose, so earlier crashes don't count against it
+    app.state::<SidecarCrashes>().0.lock_or_recover().remove(name);
+
+    let port_rx = spawn_sidecar(app, descriptor, 0).inspect_err(|e| report_spawn_failure(app, descriptor, e))?;
+    port_rx
+        .await
+        .unwrap_or_else(|_| Err("Sidecar stopped before reporting its port".to_string()))
+}
+
+// Marks a sidecar as spawning while held
+struct SpawnGuard {
+    spawning: Arc<Mutex<HashSet<String>>>,
+    name: String,
+}
+
+impl SpawnGuard {
+    fn acquire(app: &AppHandle, name: &str) -> Result<Self, String> {
+        Self::claim(&app.state::<SidecarSpawning>().0, name)
+    }
+
+    fn claim(spawning: &Arc<Mutex<HashSet<String>>>, name: &str) -> Result<Self, String> {
+        if !spawning.lock_or_recover().insert(name.to_string()) {
+            return Err(format!("Sidecar {} is already being started", name));
+        }
+        Ok(Self {
+            spawning: Arc::clone(spawning),
+            name: name.to_string(),
+        })
+    }
+
+    // Keep the sidecar marked as spawning until the new process reports its
+    // port, exits or times out, as launch() does by awaiting it
+    fn hold_until_reported(self, port_rx: oneshot::Receiver<Result<u16, String>>) {
+        tauri::async_runtime::spawn(async move {
+            let _ = port_rx.await;
+            drop(self);
+        });
+    }
+}
+
+impl Drop for SpawnGuard {
+    fn drop(&mut self) {
+        self.spawning.lock_or_recover().remove(&self.name);
+    }
+}
+
+// Whether this run skips the sidecars; read once at startup, before
+// SidecarDisabled is managed
+pub fn disabled_by_config(config: &SidecarConfig) -> bool {
+    let env_set = std::env::var(NO_SIDECAR_ENV_VAR).is_ok_and(|value| !value.is_empty() && value != "0");
+    env_set || config.disable_sidecar
+}
+
+pub fn is_disabled(app: &AppHandle) -> bool {
+    app.state::<SidecarDisabled>().0
+}
+
+fn ensure_enabled(app: &AppHandle) -> Result<(), String> {
+    if is_disabled(app) {
+        return Err(SIDECAR_DISABLED_ERROR.to_string());
+    }
+    Ok(())
+}
+
+// The built-in backend followed by any extra sidecars from the config
+pub fn descriptors(app: &AppHandle) -> Vec<SidecarDescriptor> {
+    let config = app.state::<Mutex<SidecarConfig>>();
+    let config = config.lock_or_recover();
+    let mut backend = SidecarDescriptor::backend();
+    backend.port_mode = config.port_mode;
+    backend.port = config.port;
+    backend.host = config.host;
+    if let Some(python) = std::env::var(PYTHON_ENV_VAR).ok().filter(|p| !p.is_empty()).or_else(|| config.python.clone()) {
+        backend.dev_program = python;
+    }
+    if let Ok(Some((program, args))) = dev_command_override() {
+        backend.dev_program = program;
+        backend.dev_args = args;
+    }
+    backend.dev_cwd = config.dev_cwd.clone();
+    let mut descriptors = vec![backend];
+    descriptors.extend(config.extra_sidecars.iter().cloned());
+    descriptors
+}
+
+// FLASH_AI_SIDECAR_CMD split into program and args, or None when it's
+// unset. An empty or malformed value is an error, and is ignored.
+fn dev_command_override() -> Result<Option<(String, Vec<String>)>, String> {
+    let Ok(line) = std::env::var(SIDECAR_CMD_ENV_VAR) else {
+        return Ok(None);
+    };
+    let mut words = split_command_line(&line)?.into_iter();
+    let program = words.next().ok_or_else(|| "it's empty".to_string())?;
+    Ok(Some((program, words.collect())))
+}
+
+// Split on whitespace, keeping "double" or 'single' quoted parts together.
+// Backslashes are left alone so Windows paths work.
+fn split_command_line(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_word = true;
+            }
+            None if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            None => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if let Some(q) = quote {
+        return Err(format!("unclosed {} quote", q));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+// Log how the backend will be started in dev builds, so a bad
+// FLASH_AI_SIDECAR_CMD shows up before anything fails to spawn
+#[cfg(debug_assertions)]
+pub fn log_dev_command(app: &AppHandle) {
+    if let Err(e) = dev_command_override() {
+        warn!("Ignoring {}: {}", SIDECAR_CMD_ENV_VAR, e);
+    }
+    let Ok(backend) = descriptor(app, DEFAULT_SIDECAR) else {
+        return;
+    };
+    let command_line = std::iter::once(&backend.dev_program)
+        .chain(&backend.dev_args)
+        .map(|word| {
+            if word.contains(char::is_whitespace) {
+                format!("\"{}\"", word)
+            } else {
+                word.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let cwd = backend.dev_cwd.clone().unwrap_or_else(dev_root);
+    info!(command = %command_line, cwd = %cwd.display(), "Dev sidecar command");
+}
+
+fn descriptor(app: &AppHandle, name: &str) -> Result<SidecarDescriptor, String> {
+    descriptors(app)
+        .into_iter()
+        .find(|d| d.name == name)
+        .ok_or_else(|| format!("Unknown sidecar: {}", name))
+}
+
+pub fn addr_of(app: &AppHandle, name: &str) -> Option<SocketAddr> {
+    app.state::<SidecarAddr>().0.borrow().get(name).copied()
+}
+
+fn port_of(app: &AppHandle, name: &str) -> Option<u16> {
+    addr_of(app, name).map(|addr| addr.port())
+}
+
+// Emit sidecar-port-changed when a sidecar comes up on another port than it
+// last ran on, whatever restarted it. Clients need the new port only once
+// the sidecar is serving, so this runs on ready rather than in set_addr.
+fn note_port(app: &AppHandle, name: &str, port: u16) {
+    let Some(old_port) = app.state::<SidecarLastPort>().moved(name, port) else {
+        return;
+    };
+    info!(sidecar = name, old_port, port, "Sidecar port changed");
+    events::emit(app, AppEvent::SidecarPortChanged(SidecarPortChanged {
+        name: name.to_string(),
+        old_port,
+        port,
+    }));
+}
+
+fn set_addr(app: &AppHandle, name: &str, addr: Option<SocketAddr>) {
+    app.state::<SidecarAddr>().0.send_if_modified(|addrs| match addr {
+        Some(addr) => addrs.insert(name.to_string(), addr) != Some(addr),
+        None => addrs.remove(name).is_some(),
+    });
+}
+
+fn is_stopping(app: &AppHandle, name: &str) -> bool {
+    app.state::<SidecarStopping>().0.lock_or_recover().contains(name)
+}
+
+// Kill the current child (if any) and forget its port. The watcher task of a
+// killed child notices the slot no longer holds it and won't restart it.
+fn kill_sidecar(app: &AppHandle, name: &str) {
+    let child = app.state::<SidecarChild>().0.lock_or_recover().remove(name);
+    if let Some(child) = child {
+        if let Err(e) = child.kill() {
+            error!(sidecar = name, "Failed to kill sidecar: {}", e);
+        }
+    }
+    if let Some(pid) = take_announced_pid(app, name) {
+        crate::orphans::kill_group(pid);
+    }
+    set_addr(app, name, None);
+    set_status(app, name, SidecarStatus::Stopped);
+}
+
+// First spawn at launch. Failures are retried in the background, emitting
+// sidecar-spawn-retry before each attempt, and reported through
+// report_spawn_failure once the retries run out.
+pub fn spawn_initial(app: &AppHandle, descriptor: SidecarDescriptor) {
+    // Retrying won't bring missing files back
+    #[cfg(not(debug_assertions))]
+    if let Err(e) = check_resources(app, &descriptor) {
+        report_spawn_failure(app, &descriptor, &e);
+        return;
+    }
+    let first_attempt = match SpawnGuard::acquire(app, &descriptor.name) {
+        Ok(spawning) => spawn_sidecar(app, &descriptor, 0).map(|port_rx| spawning.hold_until_reported(port_rx)),
+        Err(_) => return,
+    };
+    let Err(e) = first_attempt else {
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut error = e;
+        let mut delay = SPAWN_RETRY_DELAY;
+        for attempt in 1..=SPAWN_RETRY_ATTEMPTS {
+            warn!(sidecar = %descriptor.name, attempt, "Failed to spawn sidecar, retrying: {}", error);
+            events::emit(&app, AppEvent::SidecarSpawnRetry(SidecarSpawnRetry {
+                name: descriptor.name.clone(),
+                attempt,
+                max_attempts: SPAWN_RETRY_ATTEMPTS,
+                delay_ms: delay.as_millis() as u64,
+                error: error.clone(),
+            }));
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+
+            // Give up if the app is closing or the sidecar was started
+            // some other way, e.g. restart_sidecar, in the meantime
+            let Ok(_spawning) = SpawnGuard::acquire(&app, &descriptor.name) else {
+                return;
+            };
+            if is_stopping(&app, &descriptor.name)
+                || app.state::<SidecarChild>().0.lock_or_recover().contains_key(&descriptor.name)
+            {
+                return;
+            }
+            match spawn_sidecar(&app, &descriptor, 0) {
+                Ok(port_rx) => {
+                    let _ = port_rx.await;
+                    return;
+                }
+                Err(e) => error = e,
+            }
+        }
+        report_spawn_failure(&app, &descriptor, &error);
+    });
+}
+
+// A sidecar that couldn't be spawned at all is marked crashed, with the
+// error kept in its log, so the UI can show it and offer a restart
+pub fn report_spawn_failure(app: &AppHandle, descriptor: &SidecarDescriptor, message: &str) {
+    error!(sidecar = %descriptor.name, "Failed to spawn sidecar: {}", message);
+    record_output(app, descriptor, "spawn", message);
+    let (status, failed) = spawn_failure(descriptor, message);
+    set_status(app, &descriptor.name, status);
+    if descriptor.name == DEFAULT_SIDECAR {
+        crate::startup_report::failed(app, message);
+    }
+    events::emit(app, AppEvent::SidecarStartupFailed(failed));
+}
+
+// The status and `sidecar-startup-failed` payload for a sidecar that
+// couldn't be spawned
+fn spawn_failure(descriptor: &SidecarDescriptor, message: &str) -> (SidecarStatus, SidecarStartupFailed) {
+    let failed = SidecarStartupFailed {
+        name: descriptor.name.clone(),
+        error: message.to_string(),
+        elapsed_ms: 0,
+        stderr: Vec::new(),
+    };
+    (SidecarStatus::Crashed { code: None }, failed)
+}
+
+// Keep a line of sidecar output in memory and on disk, and pass it on to
+// the frontend as sidecar-log
+fn record_output(app: &AppHandle, descriptor: &SidecarDescriptor, stream: &str, line: &str) {
+    let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    {
+        let logs = app.state::<SidecarLogs>();
+        let mut logs = logs.0.lock_or_recover();
+        let logs = logs.entry(descriptor.name.clone()).or_default();
+        if logs.len() == SIDECAR_LOG_LINES {
+            logs.pop_front();
+        }
+        logs.push_back(format!("[{}] {}", stream, line));
+    }
+
+    let seq = app.state::<SidecarLogSeq>().0.fetch_add(1, Ordering::Relaxed) + 1;
+    events::emit(
+        app,
+        AppEvent::SidecarLog(SidecarLog {
+            name: descriptor.name.clone(),
+            stream: stream.to_string(),
+            line: line.to_string(),
+            timestamp: timestamp.clone(),
+            seq,
+        }),
+    );
+
+    let log_files = app.state::<SidecarLogFile>();
+    let mut log_files = log_files.0.lock_or_recover();
+    if !log_files.contains_key(&descriptor.name) {
+        let Some(log) = open_log_file(app, &descriptor.log_file_name()) else {
+            return;
+        };
+        log_files.insert(descriptor.name.clone(), log);
+    }
+    if let Some(log) = log_files.get_mut(&descriptor.name) {
+        if let Err(e) = log.write_line(&format!("{} [{}] {}", timestamp, stream, line)) {
+            warn!("Failed to write {}: {}", log.path().display(), e);
+        }
+    }
+}
+
+fn open_log_file(app: &AppHandle, file_name: &str) -> Option<RotatingLog> {
+    let dir = match app.path().app_log_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            warn!("Failed to resolve the app log dir: {}", e);
+            return None;
+        }
+    };
+    match RotatingLog::open(dir.join(file_name), SIDECAR_LOG_MAX_BYTES, SIDECAR_LOG_MAX_FILES) {
+        Ok(log) => Some(log),
+        Err(e) => {
+            warn!("Failed to open {}: {}", file_name, e);
+            None
+        }
+    }
+}
+
+fn set_status(app: &AppHandle, name: &str, status: SidecarStatus) {
+    app.state::<SidecarStatusState>().0.lock_or_recover().insert(name.to_string(), status);
+    app.state::<SidecarStatusChanged>().0.notify_waiters();
+}
+
+// Stop a sidecar and keep it from being restarted after a crash. It's
+// asked to exit through POST /shutdown first, so it can flush its state,
+// and only killed if it's still running after the grace period.
+pub async fn shutdown_sidecar(app: &AppHandle, name: &str) {
+    info!(sidecar = name, "Shutting down sidecar");
+    app.state::<SidecarStopping>().0.lock_or_recover().insert(name.to_string());
+
+    let grace_secs = app.state::<Mutex<SidecarConfig>>().lock_or_recover().shutdown_grace_secs;
+    let pid = app.state::<SidecarChild>().0.lock_or_recover().get(name).map(|child| child.pid());
+    if let (Some(pid), Some(addr)) = (pid, addr_of(app, name)) {
+        if grace_secs > 0 {
+            let token = app.state::<SidecarToken>().0.clone();
+            match request_shutdown(addr, &token).await {
+                Ok(()) => {
+                    let grace = Duration::from_secs(grace_secs);
+                    if tokio::time::timeout(grace, wait_for_exit(app, name, pid)).await.is_ok() {
+                        info!(sidecar = name, "Sidecar exited on request");
+                    } else {
+                        warn!(sidecar = name, grace_secs, "Sidecar did not exit in time, killing it");
+                    }
+                }
+                Err(e) => warn!(sidecar = name, "Failed to ask the sidecar to shut down: {}", e),
+            }
+        }
+    }
+    kill_sidecar(app, name);
+}
+
+// POST /shutdown, which makes the sidecar exit once its response is sent
+async fn request_shutdown(addr: SocketAddr, token: &str) -> Result<(), String> {
+    let client = reqwest::Client::builder()
+        .timeout(SHUTDOWN_REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let response = client
+        .post(format!("http://{}/shutdown", addr))
+        .bearer_auth(token)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("POST /shutdown returned {}", response.status()));
+    }
+    Ok(())
+}
+
+// Resolves once process `pid` no longer holds the sidecar's slot, which the
+// watcher task clears when it sees the process terminate
+async fn wait_for_exit(app: &AppHandle, name: &str, pid: u32) {
+    let changed = app.state::<SidecarStatusChanged>();
+    loop {
+        // Register before checking so a change in between isn't missed
+        let notified = changed.0.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if app.state::<SidecarChild>().0.lock_or_recover().get(name).is_none_or(|child| child.pid() != pid) {
+            return;
+        }
+        notified.await;
+    }
+}
+
+// Last-ditch cleanup from the panic hook. The panicking thread may already
+// hold the lock, so this gives up rather than wait for it.
+pub fn kill_all_on_panic(app: &AppHandle) {
+    let children = app.state::<SidecarChild>();
+    let mut children = match children.0.try_lock() {
+        Ok(children) => children,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return,
+    };
+    let info = app.state::<SidecarInfo>();
+    let info = info.0.try_lock().ok();
+    for (name, child) in children.drain() {
+        let _ = child.kill();
+        let pid = info.as_ref().and_then(|info| info.get(&name)?.get("pid")?.parse().ok());
+        if let Some(pid) = pid {
+            crate::orphans::kill_group(pid);
+        }
+    }
+}
+
+// The pid the sidecar announced with `SIDECAR_PID=`, which on Unix also
+// leads its process group. Not the same as the child's pid when the
+// bundled binary unpacks and runs the real process. Taken out of
+// SidecarInfo once the group is dealt with, so a later kill can't hit an
+// unrelated group that reused the pid.
+fn take_announced_pid(app: &AppHandle, name: &str) -> Option<u32> {
+    app.state::<SidecarInfo>().0.lock_or_recover().get_mut(name)?.remove("pid")?.parse().ok()
+}
+
+// `SIDECAR_LOADING=<percent>` carries a whole number from 0 to 100, e.g.
+// `SIDECAR_LOADING=40`. Anything else is malformed.
+fn parse_loading(value: &str) -> Option<u8> {
+    value.trim().parse::<u8>().ok().filter(|percent| *percent <= 100)
+}
+
+// For `lazy_sidecar`: report every sidecar as stopped and leave spawning
+// to ensure_sidecar_started
+pub fn defer_all(app: &AppHandle) {
+    for descriptor in descriptors(app) {
+        app.state::<SidecarLazy>().0.lock_or_recover().insert(descriptor.name.clone());
+        set_status(app, &descriptor.name, SidecarStatus::Stopped);
+    }
+}
+
+// Spawn a sidecar held back by `lazy_sidecar` if nothing has started it
+// yet. Of several concurrent first callers exactly one spawns it.
+fn start_if_lazy(app: &AppHandle, name: &str) -> Result<(), String> {
+    if !app.state::<SidecarLazy>().take(name) {
+        return Ok(());
+    }
+    let descriptor = descriptor(app, name)?;
+    info!(sidecar = name, "Starting sidecar on first use");
+    spawn_initial(app, descriptor);
+    Ok(())
+}
+
+// The sidecar's address, spawning it first if it's lazy and hasn't been
+// started. One that's starting gets the startup timeout to announce its
+// address; one that's stopped or crashed is an error right away.
+pub async fn ensure_sidecar_started(app: &AppHandle, name: &str) -> Result<SocketAddr, String> {
+    ensure_enabled(app)?;
+    start_if_lazy(app, name)?;
+    // Subscribe before checking so an address set in between isn't missed
+    let mut addrs = app.state::<SidecarAddr>().0.subscribe();
+    if let Some(addr) = addrs.borrow_and_update().get(name) {
+        return Ok(*addr);
+    }
+    if app.state::<SidecarStatusState>().0.lock_or_recover().get(name) != Some(&SidecarStatus::Starting) {
+        return Err(format!("Sidecar {} is not running", name));
+    }
+    let secs = app.state::<Mutex<SidecarConfig>>().lock_or_recover().startup_timeout_secs;
+    let wait = addrs.wait_for(|addrs| addrs.contains_key(name));
+    let addr = match tokio::time::timeout(Duration::from_secs(secs), wait).await {
+        Ok(Ok(addrs)) => addrs.get(name).copied().ok_or_else(|| format!("Sidecar {} is not running", name)),
+        _ => Err(format!("Sidecar {} did not start within {}s", name, secs)),
+    };
+    addr
+}
+
+// For runs without sidecars: report every one as stopped without spawning it
+pub fn mark_all_stopped(app: &AppHandle) {
+    for descriptor in descriptors(app) {
+        set_status(app, &descriptor.name, SidecarStatus::Stopped);
+    }
+}
+
+// Sidecars are shut down side by side, so closing takes at most one grace
+// period
+pub async fn shutdown_all_sidecars(app: &AppHandle) {
+    let tasks: Vec<_> = descriptors(app)
+        .into_iter()
+        .map(|descriptor| {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move { shutdown_sidecar(&app, &descriptor.name).await })
+        })
+        .collect();
+    for task in tasks {
+        let _ = task.await;
+    }
+}
+
+// Start the sidecars again after shutdown_all_sidecars when the app isn't
+// exiting after all
+pub fn resume_sidecars(app: &AppHandle) {
+    if is_disabled(app) {
+        return;
+    }
+    for descriptor in descriptors(app) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = launch(&app, &descriptor).await {
+                error!(sidecar = %descriptor.name, "Failed to restart sidecar: {}", e);
+            }
+        });
+    }
+    start_health_poller(app);
+}
+
+// Close the sidecar log files before exiting. Every line is flushed as
+// it's written, so this only releases the handles.
+pub fn close_log_files(app: &AppHandle) {
+    app.state::<SidecarLogFile>().0.lock_or_recover().clear();
+}
+
+impl SidecarConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.invalid_fields().into_iter().next() {
+            Some((_, e)) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    // Each field that fails validation, with why
+    pub fn invalid_fields(&self) -> Vec<(&'static str, String)> {
+        let mut invalid = Vec::new();
+        if self.startup_timeout_secs == 0 {
+            invalid.push(("startup_timeout_secs", "startup_timeout_secs must be positive".to_string()));
+        }
+        if let Some(filter) = &self.log_filter {
+            if let Err(e) = tracing_subscriber::EnvFilter::try_new(filter) {
+                invalid.push(("log_filter", format!("invalid log_filter: {}", e)));
+            }
+        }
+        let mut names = HashSet::from([DEFAULT_SIDECAR]);
+        for sidecar in &self.extra_sidecars {
+            if sidecar.name.is_empty() {
+                invalid.push(("extra_sidecars", "sidecar names must not be empty".to_string()));
+                break;
+            }
+            if !names.insert(&sidecar.name) {
+                invalid.push(("extra_sidecars", format!("duplicate sidecar name: {}", sidecar.name)));
+                break;
+            }
+        }
+        invalid
+    }
+
+    // sidecar-config.json next to the executable, read when there's no
+    // config.json yet. Missing or unreadable files fall back to the defaults.
+    pub fn load() -> Self {
+        let Some(path) = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join(SIDECAR_CONFIG_FILE)))
+        else {
+            return Self::default();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str(&contents) {
+                Ok(section) => config::lenient(&path, section, Self::invalid_fields),
+                Err(e) => {
+                    warn!("Ignoring invalid {}: {}", path.display(), e);
+                    Self::default()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                warn!("Failed to read {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+}
+
+impl SidecarEnv {
+    // Allow-listed variables from our own environment, then overrides from
+    // the bundled sidecar.env. Later entries win.
+    //
+    // Variable names are case-insensitive on Windows but case-sensitive on
+    // Unix, so `hf_home=...` in sidecar.env replaces `HF_HOME` on Windows
+    // and sets a second, separate variable everywhere else. Keep names
+    // upper-case to get the same behavior on every platform.
+    pub fn load(app: &AppHandle) -> Self {
+        let mut vars = HashMap::new();
+        for key in FORWARDED_ENV_VARS {
+            if let Ok(value) = std::env::var(key) {
+                vars.insert(key.to_string(), value);
+            }
+        }
+
+        if let Ok(dir) = app.path().resource_dir() {
+            let path = dir.join(SIDECAR_ENV_FILE);
+            match std::fs::read_to_string(&path) {
+                Ok(contents) => vars.extend(parse_env_file(&contents)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => warn!("Failed to read {}: {}", path.display(), e),
+            }
+        }
+
+        Self { vars }
+    }
+}
+
+impl SidecarCliArgs {
+    // Every `--sidecar-arg KEY=VALUE` (or `--sidecar-arg=KEY=VALUE`), in
+    // order, each forwarded as a single `--KEY=VALUE` so a value can never
+    // be mistaken for a flag of its own. Keys are letters, digits, `-` and
+    // `_`, starting with a letter; anything else is logged and dropped.
+    pub fn from_args() -> Self {
+        let mut forwarded = Vec::new();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let pair = if arg == SIDECAR_ARG_FLAG {
+                args.next()
+            } else {
+                arg.strip_prefix(SIDECAR_ARG_FLAG)
+                    .and_then(|rest| rest.strip_prefix('='))
+                    .map(str::to_string)
+            };
+            let Some(pair) = pair else {
+                if arg == SIDECAR_ARG_FLAG {
+                    warn!("{} needs a KEY=VALUE argument", SIDECAR_ARG_FLAG);
+                }
+                continue;
+            };
+            match parse_sidecar_arg(&pair) {
+                Ok(arg) => forwarded.push(arg),
+                Err(e) => warn!("Ignoring {} {}: {}", SIDECAR_ARG_FLAG, pair, e),
+            }
+        }
+        if !forwarded.is_empty() {
+            info!(args = ?forwarded, "Forwarding command line arguments to the sidecar");
+        }
+        Self(forwarded)
+    }
+}
+
+fn parse_sidecar_arg(pair: &str) -> Result<String, String> {
+    let (key, value) = pair.split_once('=').ok_or("expected KEY=VALUE")?;
+    let valid = key.starts_with(|c: char| c.is_ascii_alphabetic())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("'{}' isn't a valid option name", key));
+    }
+    if RESERVED_SIDECAR_ARGS.contains(&key) {
+        return Err(format!("--{} is set by the app", key));
+    }
+    Ok(format!("--{}={}", key, value))
+}
+
+// Parse `KEY=VALUE` lines, skipping blanks and `#` comments. Values may be
+// wrapped in single or double quotes.
+fn parse_env_file(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            let key = key.trim().trim_start_matches("export ").trim();
+            if key.is_empty() {
+                return None;
+            }
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+// The command to spawn, along with a description of it for
+// get_sidecar_command_info
+fn sidecar_command(app: &AppHandle, descriptor: &SidecarDescriptor) -> Result<(Command, SidecarCommandInfo), String> {
+    let shell = app.shell();
+
+    // In dev mode, run the program directly (e.g. the Python module)
+    #[cfg(debug_assertions)]
+    let (command, program, args, cwd): (Result<_, tauri_plugin_shell::Error>, _, _, _) = (
+        Ok(shell.command(&descriptor.dev_program)),
+        &descriptor.dev_program,
+        &descriptor.dev_args,
+        descriptor.dev_cwd.clone().unwrap_or_else(dev_root),
+    );
+
+    // In production, use the bundled sidecar binary, run from the resource
+    // dir so relative paths to its bundled files resolve
+    #[cfg(not(debug_assertions))]
+    let (command, program, args, cwd) = {
+        let resource_dir = check_resources(app, descriptor)?;
+        (shell.sidecar(&descriptor.binary), &descriptor.binary, &descriptor.args, resource_dir)
+    };
+
+    let overrides = app.state::<SidecarEnvOverrides>().0.lock_or_recover().get(&descriptor.name).cloned();
+    // The model picked with select_model, unless overridden
+    let model_dir = (descriptor.name == DEFAULT_SIDECAR)
+        .then(|| crate::models::selected_model_dir(app))
+        .flatten()
+        .map(|dir| (crate::models::MODEL_DIR_ENV_VAR.to_string(), dir.display().to_string()));
+    // In order of precedence, lowest first
+    let mut env: Vec<(String, String)> = app.state::<SidecarEnv>().vars.clone().into_iter().collect();
+    env.extend(model_dir);
+    env.extend(overrides.unwrap_or_default());
+    env.push((AUTH_TOKEN_ENV_VAR.to_string(), app.state::<SidecarToken>().0.clone()));
+    env.push((crate::orphans::PARENT_PID_ENV_VAR.to_string(), std::process::id().to_string()));
+
+    let command = command
+        .map(|command| command.args(args).current_dir(&cwd).envs(env.clone()))
+        .map_err(|e| describe_spawn_error(descriptor, &e))?;
+    let info = SidecarCommandInfo {
+        program: locate_program(descriptor)
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|_| program.clone()),
+        args: args.clone(),
+        cwd: cwd.display().to_string(),
+        env: env
+            .into_iter()
+            .map(|(key, value)| {
+                let value = if is_secret(&key) { REDACTED.to_string() } else { value };
+                (key, value)
+            })
+            .collect(),
+    };
+    Ok((command, info))
+}
+
+// The auth token, plus anything else named like a credential
+fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    key == AUTH_TOKEN_ENV_VAR || SECRET_ENV_MARKERS.iter().any(|marker| key.contains(marker))
+}
+
+// The resource dir, if everything the sidecar needs from it is there.
+// Otherwise an error naming each missing path. Dev builds run from source.
+#[cfg(not(debug_assertions))]
+pub fn check_resources(app: &AppHandle, descriptor: &SidecarDescriptor) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .resource_dir()
+        .map_err(|e| format!("Failed to resolve the resource dir: {}", e))?;
+    let missing: Vec<String> = descriptor
+        .resources
+        .iter()
+        .filter(|resource| !dir.join(resource).exists())
+        .map(|resource| resource.display().to_string())
+        .collect();
+    if missing.is_empty() {
+        Ok(dir)
+    } else {
+        Err(format!(
+            "The '{}' sidecar is missing bundled files ({}) under {}; try reinstalling",
+            descriptor.name,
+            missing.join(", "),
+            dir.display()
+        ))
+    }
+}
+
+// Turn the shell plugin's errors into something a user can act on, chiefly
+// a missing sidecar binary
+fn describe_spawn_error(descriptor: &SidecarDescriptor, error: &tauri_plugin_shell::Error) -> String {
+    #[cfg(debug_assertions)]
+    let program = &descriptor.dev_program;
+    #[cfg(not(debug_assertions))]
+    let program = &descriptor.binary;
+    match error {
+        tauri_plugin_shell::Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if cfg!(debug_assertions) {
+                format!("Could not find '{}'; is it installed and on PATH?", program)
+            } else {
+                format!("The '{}' sidecar is missing from the app bundle; try reinstalling", program)
+            }
+        }
+        tauri_plugin_shell::Error::SidecarNotAllowed(_) => {
+            format!("'{}' is not listed under bundle > externalBin in tauri.conf.json", program)
+        }
+        e => format!("Failed to start '{}': {}", program, e),
+    }
+}
+
+// Where the sidecar's program would be started from, without starting it.
+// Release builds look next to our executable, as the shell plugin does;
+// dev builds search PATH unless the program is already a path.
+pub fn locate_program(descriptor: &SidecarDescriptor) -> Result<PathBuf, String> {
+    #[cfg(debug_assertions)]
+    let candidates: Vec<PathBuf> = {
+        let program = std::path::Path::new(&descriptor.dev_program);
+        if program.components().count() > 1 {
+            vec![descriptor.dev_cwd.clone().unwrap_or_else(dev_root).join(program)]
+        } else {
+            let path = std::env::var_os("PATH").unwrap_or_default();
+            std::env::split_paths(&path).map(|dir| dir.join(program)).collect()
+        }
+    };
+    #[cfg(not(debug_assertions))]
+    let candidates: Vec<PathBuf> = {
+        let exe = std::env::current_exe().map_err(|e| format!("Failed to resolve our own executable: {}", e))?;
+        exe.parent().map(|dir| dir.join(&descriptor.binary)).into_iter().collect()
+    };
+
+    candidates
+        .into_iter()
+        .map(|mut path| {
+            if cfg!(windows) && path.extension().is_none_or(|ext| ext != "exe") {
+                path.as_mut_os_string().push(".exe");
+            }
+            path
+        })
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            let error = std::io::Error::from(std::io::ErrorKind::NotFound);
+            describe_spawn_error(descriptor, &tauri_plugin_shell::Error::Io(error))
+        })
+}
+
+// Repo root, where python_sidecar/ lives
+#[cfg(debug_assertions)]
+fn dev_root() -> PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("..")
+}
+
+// The configured port if it's free, otherwise an ephemeral one. A taken
+// port is reported with `sidecar-port-conflict` rather than letting the
+// sidecar fail to bind it.
+fn assign_port(app: &AppHandle, descriptor: &SidecarDescriptor) -> std::io::Result<u16> {
+    if let Some(port) = descriptor.port {
+        if std::net::TcpListener::bind((descriptor.host(), port)).is_ok() {
+            return Ok(port);
+        }
+        warn!(sidecar = %descriptor.name, port, "Port is already in use, picking another");
+        events::emit(app, AppEvent::SidecarPortConflict(SidecarPortConflict {
+            name: descriptor.name.clone(),
+            port,
+        }));
+    }
+    reserve_port(descriptor.host())
+}
+
+// Let the OS pick a free port. The listener is closed before the sidecar
+// binds it, so there's a small window where another process could take it.
+fn reserve_port(host: IpAddr) -> std::io::Result<u16> {
+    let listener = std::net::TcpListener::bind((host, 0))?;
+    Ok(listener.local_addr()?.port())
+}
+
+// Delay before restart attempt `attempt` (0-based): 1s, 2s, 4s, ... capped
+fn restart_backoff(attempt: u32) -> Duration {
+    let secs = 1u64.checked_shl(attempt).unwrap_or(u64::MAX);
+    Duration::from_secs(secs.min(MAX_RESTART_BACKOFF_SECS))
+}
+
+// Spawn the sidecar and watch its output. `restarts` is the number of
+// consecutive crash restarts that led to this spawn; it resets once the
+// new process reports its port. The returned receiver resolves with that
+// port, or the error if it isn't reported within the startup timeout; it's
+// dropped if the process exits first.
+pub fn spawn_sidecar(
+    app: &AppHandle,
+    descriptor: &SidecarDescriptor,
+    restarts: u32,
+) -> Result<oneshot::Receiver<Result<u16, String>>, String> {
+    let (mut command, mut info) = sidecar_command(app, descriptor)?;
+    let assigned_addr = match descriptor.port_mode {
+        PortMode::Assign => {
+            let port = assign_port(app, descriptor).map_err(|e| format!("Failed to pick a port: {}", e))?;
+            Some(SocketAddr::new(descriptor.host(), port))
+        }
+        PortMode::Announce => None,
+    };
+    let mut args = Vec::new();
+    if let Some(host) = descriptor.host {
+        args.extend(["--host".to_string(), host.to_string()]);
+    }
+    if let Some(addr) = assigned_addr {
+        args.extend(["--port".to_string(), addr.port().to_string()]);
+    }
+    // Extra sidecars take their own flags from `args`
+    if descriptor.name == DEFAULT_SIDECAR {
+        let (level, device, demo_mode) = {
+            let config = app.state::<Mutex<SidecarConfig>>();
+            let config = config.lock_or_recover();
+            (config.log_level, config.device, config.demo_mode)
+        };
+        args.extend(["--log-level".to_string(), level.as_str().to_string()]);
+        args.extend(["--device".to_string(), device.as_str().to_string()]);
+        if demo_mode {
+            args.push("--read-only".to_string());
+        }
+        args.extend(app.state::<SidecarCliArgs>().0.iter().cloned());
+    }
+    command = command.args(&args);
+    info.args.extend(args);
+    // Kept even if the spawn fails, since that's when it's most useful
+    app.state::<SidecarCommandLine>().0.lock_or_recover().insert(descriptor.name.clone(), info);
+
+    let (mut rx, child) = command.spawn().map_err(|e| describe_spawn_error(descriptor, &e))?;
+    let pid = child.pid();
+    crate::orphans::adopt(app, pid);
+    let name = descriptor.name.clone();
+    let span = info_span!("sidecar", name = %name, pid);
+    span.in_scope(|| info!(restarts, assigned = ?assigned_addr, "Spawned sidecar"));
+    app.state::<SidecarChild>().0.lock_or_recover().insert(name.clone(), child);
+    app.state::<SidecarInfo>().0.lock_or_recover().remove(&name);
+    set_status(app, &name, SidecarStatus::Starting);
+    if assigned_addr.is_some() {
+        set_addr(app, &name, assigned_addr);
+    }
+
+    let (port_tx, port_rx) = oneshot::channel();
+    let app = app.clone();
+    let descriptor = descriptor.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut restarts = restarts;
+        let mut port_tx = Some(port_tx);
+        let startup_timeout_secs = app.state::<Mutex<SidecarConfig>>().lock_or_recover().startup_timeout_secs;
+        let started = Instant::now();
+        let mut deadline = started + Duration::from_secs(startup_timeout_secs);
+        // Wait for the port only until the deadline; afterwards keep reading
+        // without a timeout so a late start still gets picked up.
+        let mut waiting_for_port = true;
+        let mut startup_stderr = VecDeque::new();
+        let mut announced_addr: Option<SocketAddr> = None;
+        // Only the first ready of each process counts as its startup time
+        let mut ready_reported = false;
+
+        loop {
+            let event = if waiting_for_port {
+                match tokio::time::timeout_at(deadline, rx.recv()).await {
+                    Ok(event) => event,
+                    Err(_) => {
+                        waiting_for_port = false;
+                        let message = format!("Sidecar did not report its port within {}s", startup_timeout_secs);
+                        error!("{}", message);
+                        if let Some(tx) = port_tx.take() {
+                            let _ = tx.send(Err(message.clone()));
+                        }
+                        if name == DEFAULT_SIDECAR {
+                            crate::startup_report::failed(&app, &message);
+                        }
+                        events::emit(&app, AppEvent::SidecarStartupFailed(SidecarStartupFailed {
+                            name: name.clone(),
+                            error: message,
+                            elapsed_ms: started.elapsed().as_millis() as u64,
+                            stderr: startup_stderr.drain(..).collect(),
+                        }));
+                        continue;
+                    }
+                }
+            } else {
+                rx.recv().await
+            };
+            let Some(event) = event else { break };
+
+            match event {
+                CommandEvent::Stdout(line) => {
+                    let line_str = String::from_utf8_lossy(&line);
+                    record_output(&app, &descriptor, "stdout", line_str.trim_end());
+                    // A fast startup can deliver several buffered lines in
+                    // one chunk, with announcements anywhere among them
+                    let mut ready = false;
+                    for (key, value) in line_str.lines().filter_map(|line| descriptor.announcement(line)) {
+                        if !ANNOUNCED_KEYS.contains(&key) {
+                            warn!(key, "Ignoring unknown sidecar announcement");
+                            continue;
+                        }
+                        // Progress is passed on, not kept. A sidecar that's
+                        // still making progress isn't stuck, so each step
+                        // also restarts the startup timeout.
+                        if key == "LOADING" {
+                            let Some(percent) = parse_loading(value) else {
+                                warn!(value, "Ignoring malformed sidecar loading progress");
+                                continue;
+                            };
+                            if waiting_for_port {
+                                deadline = Instant::now() + Duration::from_secs(startup_timeout_secs);
+                            }
+                            events::emit(&app, AppEvent::SidecarLoading(SidecarLoading {
+                                name: name.clone(),
+                                percent,
+                            }));
+                            continue;
+                        }
+                        app.state::<SidecarInfo>()
+                            .0
+                            .lock_or_recover()
+                            .entry(name.clone())
+                            .or_default()
+                            .insert(key.to_lowercase(), value.to_string());
+                        match key {
+                            "PORT" | "ADDR" => {
+                                let Some(addr) = descriptor.parse_addr(key, value) else {
+                                    warn!(key, value, "Ignoring invalid sidecar address");
+                                    continue;
+                                };
+                                if let Some(assigned) = assigned_addr.filter(|&assigned| assigned != addr) {
+                                    warn!(%assigned, reported = %addr, "Sidecar bound a different address than assigned");
+                                }
+                                set_addr(&app, &name, Some(addr));
+                                announced_addr = Some(addr);
+                                ready |= !descriptor.ready_signal;
+                            }
+                            "READY" => ready |= value == "1",
+                            _ => {}
+                        }
+                    }
+                    if !ready {
+                        continue;
+                    }
+                    if let Some(addr) = announced_addr.or(assigned_addr) {
+                        let port = addr.port();
+                        set_status(&app, &name, SidecarStatus::Running(port));
+                        note_port(&app, &name, port);
+                        restarts = 0;
+                        waiting_for_port = false;
+                        startup_stderr.clear();
+                        if let Some(tx) = port_tx.take() {
+                            let _ = tx.send(Ok(port));
+                        }
+                        let startup_ms = started.elapsed().as_millis() as u64;
+                        events::emit(&app, AppEvent::SidecarReady(SidecarReady {
+                            name: name.clone(),
+                            port,
+                            startup_ms,
+                        }));
+                        info!(%addr, startup_ms, "Sidecar ready");
+                        if name == DEFAULT_SIDECAR && !ready_reported {
+                            startup_metrics::record(&app, startup_ms);
+                            crate::startup_report::complete(&app, port);
+                        }
+                        ready_reported = true;
+                    } else {
+                        warn!("Sidecar reported ready before its address");
+                    }
+                }
+                CommandEvent::Stderr(line) => {
+                    let line_str = String::from_utf8_lossy(&line).trim_end().to_string();
+                    record_output(&app, &descriptor, "stderr", &line_str);
+                    // Lines from extra sidecars are tagged with their name
+                    if name == DEFAULT_SIDECAR {
+                        events::emit(&app, AppEvent::SidecarStderr(line_str.clone()));
+                    } else {
+                        events::emit(&app, AppEvent::SidecarStderr(format!("[{}] {}", name, line_str)));
+                    }
+
+                    if waiting_for_port {
+                        if startup_stderr.len() == STARTUP_STDERR_LINES {
+                            startup_stderr.pop_front();
+                        }
+                        startup_stderr.push_back(line_str);
+                    }
+                }
+                CommandEvent::Terminated(payload) => {
+                    // Whoever started this process stops waiting for its port
+                    // (and releases its SpawnGuard) before any restart below
+                    drop(port_tx.take());
+                    let ours = {
+                        let child_state = app.state::<SidecarChild>();
+                        let mut children = child_state.0.lock_or_recover();
+                        let ours = children.get(&name).is_some_and(|c| c.pid() == pid);
+                        if ours {
+                            children.remove(&name);
+                        }
+                        ours
+                    };
+                    let exit = SidecarExit::classify(ours, is_stopping(&app, &name));
+                    events::emit(&app, AppEvent::SidecarExited(SidecarExited {
+                        name: name.clone(),
+                        code: payload.code,
+                        signal: payload.signal,
+                        intentional: exit.is_intentional(),
+                    }));
+
+                    if exit == SidecarExit::Killed {
+                        info!(code = ?payload.code, "Sidecar exited after being stopped");
+                        break;
+                    }
+                    // Take down anything it left behind in its group
+                    if let Some(pid) = take_announced_pid(&app, &name) {
+                        crate::orphans::kill_group(pid);
+                    }
+                    set_addr(&app, &name, None);
+                    if exit == SidecarExit::Stopped {
+                        info!(code = ?payload.code, "Sidecar exited after being stopped");
+                        set_status(&app, &name, SidecarStatus::Stopped);
+                    } else {
+                        error!(code = ?payload.code, signal = ?payload.signal, "Sidecar exited unexpectedly");
+                        events::emit(&app, AppEvent::SidecarCrashed(SidecarCrashed {
+                            name: name.clone(),
+                            code: payload.code,
+                            signal: payload.signal,
+                        }));
+                        let crashes = record_crash(&app, &name);
+                        if restart_limit_reached(&app, crashes) {
+                            give_up(&app, &name, crashes, payload.code);
+                        } else {
+                            mark_crashed(&app, &name, payload.code);
+                            restart_sidecar_after_crash(app, descriptor, restarts).await;
+                        }
+                    }
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }.instrument(span));
+
+    Ok(port_rx)
+}
+
+// How a sidecar process ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SidecarExit {
+    // Its slot no longer held it, so it was killed on purpose (and possibly
+    // already replaced); leave everything alone
+    Killed,
+    // Exited on its own after a shutdown request
+    Stopped,
+    // Exited while it was meant to be running
+    Crashed,
+}
+
+impl SidecarExit {
+    // `ours`: the slot still held this process; `stopping`: the sidecar is in
+    // SidecarStopping
+    fn classify(ours: bool, stopping: bool) -> Self {
+        match (ours, stopping) {
+            (false, _) => Self::Killed,
+            (true, true) => Self::Stopped,
+            (true, false) => Self::Crashed,
+        }
+    }
+
+    fn is_intentional(self) -> bool {
+        self != Self::Crashed
+    }
+}
+
+// A crash that will be followed by a restart shows as Starting for
+// `crash_grace_ms`, so the UI doesn't flash "crashed" for a sidecar that's
+// back a moment later. It's marked crashed once the window passes without a
+// new process.
+fn mark_crashed(app: &AppHandle, name: &str, code: Option<i32>) {
+    let grace_ms = app.state::<Mutex<SidecarConfig>>().lock_or_recover().crash_grace_ms;
+    if grace_ms == 0 {
+        set_status(app, name, SidecarStatus::Crashed { code });
+        return;
+    }
+    set_status(app, name, SidecarStatus::Starting);
+    let app = app.clone();
+    let name = name.to_string();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(grace_ms)).await;
+        let respawned = app.state::<SidecarChild>().0.lock_or_recover().contains_key(&name);
+        let starting = app.state::<SidecarStatusState>().0.lock_or_recover().get(&name) == Some(&SidecarStatus::Starting);
+        if !respawned && starting && !is_stopping(&app, &name) {
+            set_status(&app, &name, SidecarStatus::Crashed { code });
+        }
+    });
+}
+
+// Note a crash and return how many the sidecar has had within the restart
+// window, this one included
+fn record_crash(app: &AppHandle, name: &str) -> u32 {
+    let window_secs = app.state::<Mutex<SidecarConfig>>().lock_or_recover().restart_window_secs;
+    let state = app.state::<SidecarCrashes>();
+    let mut crashes = state.0.lock_or_recover();
+    let times = crashes.entry(name.to_string()).or_default();
+    crashes_in_window(times, Instant::now(), Duration::from_secs(window_secs))
+}
+
+// Add a crash at `now` to `times`, forget the ones older than `window`, and
+// return how many are left
+fn crashes_in_window(times: &mut VecDeque<Instant>, now: Instant, window: Duration) -> u32 {
+    times.retain(|&time| now.duration_since(time) < window);
+    times.push_back(now);
+    times.len() as u32
+}
+
+fn restart_limit_reached(app: &AppHandle, crashes: u32) -> bool {
+    over_restart_limit(crashes, app.state::<Mutex<SidecarConfig>>().lock_or_recover().max_restarts)
+}
+
+fn over_restart_limit(crashes: u32, max_restarts: u32) -> bool {
+    crashes > max_restarts
+}
+
+// Leave a crash-looping sidecar crashed; start_sidecar or restart_sidecar
+// brings it back with a fresh allowance
+fn give_up(app: &AppHandle, name: &str, crashes: u32, code: Option<i32>) {
+    let window_secs = app.state::<Mutex<SidecarConfig>>().lock_or_recover().restart_window_secs;
+    error!(sidecar = name, crashes, window_secs, "Sidecar keeps crashing, giving up");
+    set_status(app, name, SidecarStatus::Crashed { code });
+    if name == DEFAULT_SIDECAR {
+        let error = format!("Sidecar crashed {} times within {}s", crashes, window_secs);
+        crate::startup_report::failed(app, &error);
+    }
+    events::emit(app, AppEvent::SidecarGivingUp(SidecarGivingUp {
+        name: name.to_string(),
+        crashes,
+        window_secs,
+    }));
+}
+
+async fn restart_sidecar_after_crash(app: AppHandle, descriptor: SidecarDescriptor, mut attempt: u32) {
+    let name = descriptor.name.as_str();
+    loop {
+        let delay = restart_backoff(attempt);
+        info!(sidecar = name, attempt = attempt + 1, delay_ms = delay.as_millis() as u64, "Restarting sidecar");
+        events::emit(&app, AppEvent::SidecarRestarting(SidecarRestarting {
+            name: name.to_string(),
+            attempt: attempt + 1,
+            delay_ms: delay.as_millis() as u64,
+        }));
+        tokio::time::sleep(delay).await;
+
+        // The app may have started closing, or someone else may have
+        // restarted the sidecar, while we were waiting
+        let Ok(_spawning) = SpawnGuard::acquire(&app, name) else {
+            return;
+        };
+        if is_stopping(&app, name) || app.state::<SidecarChild>().0.lock_or_recover().contains_key(name) {
+            return;
+        }
+
+        match spawn_sidecar(&app, &descriptor, attempt + 1) {
+            Ok(port_rx) => {
+                let _ = port_rx.await;
+                return;
+            }
+            Err(e) => {
+                error!(sidecar = name, "Failed to restart sidecar: {}", e);
+                attempt += 1;
+                let crashes = record_crash(&app, name);
+                if restart_limit_reached(&app, crashes) {
+                    give_up(&app, name, crashes, None);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_guard_refuses_a_second_spawn_until_dropped() {
+        let spawning = Arc::new(Mutex::new(HashSet::new()));
+        let guard = SpawnGuard::claim(&spawning, DEFAULT_SIDECAR).unwrap();
+        let err = SpawnGuard::claim(&spawning, DEFAULT_SIDECAR).err().unwrap();
+        assert_eq!(err, format!("Sidecar {} is already being started", DEFAULT_SIDECAR));
+        // Other sidecars are tracked separately
+        assert!(SpawnGuard::claim(&spawning, "other").is_ok());
+
+        drop(guard);
+        assert!(spawning.lock_or_recover().is_empty());
+        assert!(SpawnGuard::claim(&spawning, DEFAULT_SIDECAR).is_ok());
+    }
+
+    #[test]
+    fn parses_announcements() {
+        let descriptor = SidecarDescriptor::backend();
+        assert_eq!(descriptor.announcement("SIDECAR_PORT=8000"), Some(("PORT", "8000")));
+        assert_eq!(descriptor.announcement("SIDECAR_MODEL= all-MiniLM-L6-v2 "), Some(("MODEL", "all-MiniLM-L6-v2")));
+        assert_eq!(descriptor.announcement("SIDECAR_PORT[backend]=8001"), Some(("PORT", "8001")));
+        // Meant for another sidecar, or not an announcement at all
+        assert_eq!(descriptor.announcement("SIDECAR_PORT[ocr]=8002"), None);
+        assert_eq!(descriptor.announcement("SIDECAR_port=8000"), None);
+        assert_eq!(descriptor.announcement("SIDECAR_=8000"), None);
+        assert_eq!(descriptor.announcement("INFO: SIDECAR_PORT=8000"), None);
+
+        // Lines buffered into one chunk are each checked
+        let chunk = "INFO: loading model\nSIDECAR_PID=42\nSIDECAR_PORT=8123\n";
+        let addr = chunk
+            .lines()
+            .filter_map(|line| descriptor.announcement(line))
+            .find_map(|(key, value)| descriptor.parse_addr(key, value));
+        assert_eq!(addr, Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8123)));
+    }
+
+    #[test]
+    fn port_announced_before_subscribing_is_seen() {
+        let (addrs, _) = watch::channel(HashMap::new());
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8123);
+        addrs.send_modify(|addrs| {
+            addrs.insert(DEFAULT_SIDECAR.to_string(), addr);
+        });
+
+        let mut rx = addrs.subscribe();
+        let port = tauri::async_runtime::block_on(wait_for_port(&mut rx, DEFAULT_SIDECAR, Duration::ZERO));
+        assert_eq!(port, Some(8123));
+    }
+
+    #[test]
+    fn port_announced_after_subscribing_is_waited_for() {
+        let (addrs, mut rx) = watch::channel(HashMap::new());
+        tauri::async_runtime::block_on(async move {
+            let announce = tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                addrs.send_modify(|addrs| {
+                    addrs.insert(DEFAULT_SIDECAR.to_string(), SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8124));
+                });
+            });
+            assert_eq!(wait_for_port(&mut rx, DEFAULT_SIDECAR, Duration::from_secs(5)).await, Some(8124));
+            announce.await.unwrap();
+            assert_eq!(wait_for_port(&mut rx, "other", Duration::from_millis(20)).await, None);
+        });
+    }
+
+    #[test]
+    fn classifies_sidecar_exits() {
+        // Asked to stop through SidecarStopping
+        assert_eq!(SidecarExit::classify(true, true), SidecarExit::Stopped);
+        assert!(SidecarExit::classify(true, true).is_intentional());
+        // Killed and taken out of its slot, whether or not it was stopping
+        assert_eq!(SidecarExit::classify(false, false), SidecarExit::Killed);
+        assert_eq!(SidecarExit::classify(false, true), SidecarExit::Killed);
+        assert!(SidecarExit::classify(false, false).is_intentional());
+        // Exited while it was meant to be running
+        assert_eq!(SidecarExit::classify(true, false), SidecarExit::Crashed);
+        assert!(!SidecarExit::classify(true, false).is_intentional());
+    }
+
+    #[test]
+    fn concurrent_lazy_starts_spawn_once() {
+        const CALLERS: usize = 8;
+        let lazy = Arc::new(SidecarLazy(Mutex::new(HashSet::from([DEFAULT_SIDECAR.to_string()]))));
+        let barrier = Arc::new(std::sync::Barrier::new(CALLERS));
+        let callers: Vec<_> = (0..CALLERS)
+            .map(|_| {
+                let lazy = Arc::clone(&lazy);
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    lazy.take(DEFAULT_SIDECAR)
+                })
+            })
+            .collect();
+        let spawns = callers.into_iter().map(|caller| caller.join().unwrap()).filter(|&spawned| spawned).count();
+        assert_eq!(spawns, 1);
+        assert!(!lazy.take(DEFAULT_SIDECAR));
+    }
+
+    #[test]
+    fn gives_up_after_too_many_crashes_in_the_window() {
+        let window = Duration::from_secs(DEFAULT_RESTART_WINDOW_SECS);
+        let start = Instant::now();
+        let mut times = VecDeque::new();
+        for crash in 1..=DEFAULT_MAX_RESTARTS {
+            let crashes = crashes_in_window(&mut times, start + Duration::from_secs(crash.into()), window);
+            assert_eq!(crashes, crash);
+            assert!(!over_restart_limit(crashes, DEFAULT_MAX_RESTARTS));
+        }
+        let crashes = crashes_in_window(&mut times, start + Duration::from_secs(60), window);
+        assert!(over_restart_limit(crashes, DEFAULT_MAX_RESTARTS));
+
+        // Once the window has passed, the earlier crashes no longer count
+        let crashes = crashes_in_window(&mut times, start + window + Duration::from_secs(61), window);
+        assert_eq!(crashes, 1);
+        assert!(!over_restart_limit(crashes, DEFAULT_MAX_RESTARTS));
+    }
+
+    #[test]
+    fn restart_backoff_grows_up_to_the_cap() {
+        let delays: Vec<_> = (0..4).map(restart_backoff).collect();
+        assert_eq!(delays, [1, 2, 4, 8].map(Duration::from_secs));
+        assert!(delays.windows(2).all(|pair| pair[0] < pair[1]));
+        let cap = Duration::from_secs(MAX_RESTART_BACKOFF_SECS);
+        assert_eq!(restart_backoff(30), cap);
+        assert_eq!(restart_backoff(u32::MAX), cap);
+    }
+
+    #[test]
+    fn port_change_is_reported_once_per_move() {
+        let last_port = SidecarLastPort(Mutex::new(HashMap::new()));
+        // First start, a restart on the same port, a move, a second ready on
+        // the new port, and another move
+        let readies = [8000, 8000, 8001, 8001, 8002];
+        let changes: Vec<_> = readies.iter().filter_map(|&port| last_port.moved(DEFAULT_SIDECAR, port)).collect();
+        assert_eq!(changes, [8000, 8001]);
+        // Each sidecar's port is tracked on its own
+        assert_eq!(last_port.moved("other", 8000), None);
+    }
+
+    #[test]
+    fn sidecar_args_forward_as_one_token() {
+        assert_eq!(parse_sidecar_arg("threads=4").unwrap(), "--threads=4");
+        assert_eq!(parse_sidecar_arg("x=--read-only").unwrap(), "--x=--read-only");
+        assert!(parse_sidecar_arg("read-only=1").is_err());
+        assert!(parse_sidecar_arg("-x=1").is_err());
+        assert!(parse_sidecar_arg("threads").is_err());
+    }
+
+    #[test]
+    fn missing_program_fails_the_spawn() {
+        let descriptor = SidecarDescriptor {
+            name: "missing".to_string(),
+            dev_program: "retention-no-such-sidecar".to_string(),
+            binary: "retention-no-such-sidecar".to_string(),
+            ..SidecarDescriptor::backend()
+        };
+        let message = locate_program(&descriptor).unwrap_err();
+        #[cfg(debug_assertions)]
+        assert_eq!(message, "Could not find 'retention-no-such-sidecar'; is it installed and on PATH?");
+        #[cfg(not(debug_assertions))]
+        assert_eq!(message, "The 'retention-no-such-sidecar' sidecar is missing from the app bundle; try reinstalling");
+
+        let (status, failed) = spawn_failure(&descriptor, &message);
+        assert_eq!(status, SidecarStatus::Crashed { code: None });
+        assert_eq!(failed.name, "missing");
+        assert_eq!(failed.error, message);
+    }
+}
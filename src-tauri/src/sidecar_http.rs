@@ -0,0 +1,284 @@
+// Proxy for HTTP calls to a sidecar
+//
+// Lets the frontend reach the backend through IPC instead of fetching
+// the sidecar's address itself, which avoids CORS and mixed-content
+// trouble. The address and auth token are filled in here.
+//
+// Also home to get_sidecar_version, which checks the backend against the
+// app version, and wait_until_idle, which the updater uses to let the
+// backend finish its work before an install.
+
+use crate::events::{self, AppEvent};
+use crate::sidecar::{self, SidecarToken, DEFAULT_SIDECAR};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_http::reqwest;
+
+// For requests that don't pass `timeout_ms`
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// How often wait_until_idle asks GET /busy, and how long each ask may take
+const BUSY_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const BUSY_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+// One client for every proxied request so connections get reused
+pub struct SidecarHttp(pub reqwest::Client);
+
+impl SidecarHttp {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(DEFAULT_REQUEST_TIMEOUT)
+            .build()
+            .expect("Failed to create HTTP client");
+        Self(client)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SidecarResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    // Decoded as UTF-8, with invalid bytes replaced
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "camelCase")]
+pub enum SidecarRequestError {
+    NotRunning,
+    // Sidecars are turned off for this run
+    Disabled,
+    InvalidRequest(String),
+    // Nothing listening on the port: the sidecar is down or restarting
+    ConnectionRefused(String),
+    // No response within the timeout, in milliseconds: the sidecar is
+    // wedged or the request is slow
+    Timeout(u64),
+    Failed(String),
+}
+
+impl std::fmt::Display for SidecarRequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SidecarRequestError::NotRunning => write!(f, "Sidecar is not running"),
+            SidecarRequestError::Disabled => write!(f, "{}", sidecar::SIDECAR_DISABLED_ERROR),
+            SidecarRequestError::InvalidRequest(e) => write!(f, "Invalid request: {}", e),
+            SidecarRequestError::ConnectionRefused(e) => write!(f, "Connection to sidecar refused: {}", e),
+            SidecarRequestError::Timeout(ms) => write!(f, "Sidecar request timed out after {}ms", ms),
+            SidecarRequestError::Failed(e) => write!(f, "Sidecar request failed: {}", e),
+        }
+    }
+}
+
+// Payload of `version-mismatch`
+#[derive(Clone, Serialize)]
+pub struct VersionMismatch {
+    app_version: String,
+    sidecar_version: String,
+}
+
+#[derive(Deserialize)]
+struct VersionResponse {
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct BusyResponse {
+    busy: bool,
+}
+
+impl SidecarRequestError {
+    // reqwest only says that a request timed out, not after how long
+    fn from_reqwest(e: reqwest::Error, timeout: Duration) -> Self {
+        if e.is_timeout() {
+            SidecarRequestError::Timeout(timeout.as_millis() as u64)
+        } else if e.is_connect() {
+            SidecarRequestError::ConnectionRefused(e.to_string())
+        } else {
+            SidecarRequestError::Failed(e.to_string())
+        }
+    }
+}
+
+// `timeout_ms` covers the whole request, from connecting to the last byte
+// of the body; 0 or None means the default
+fn request_timeout(timeout_ms: Option<u64>) -> Duration {
+    timeout_ms.filter(|&ms| ms > 0).map(Duration::from_millis).unwrap_or(DEFAULT_REQUEST_TIMEOUT)
+}
+
+// Starts a lazy sidecar on the first request
+async fn running_addr(app: &AppHandle, name: &str) -> Result<SocketAddr, SidecarRequestError> {
+    if sidecar::is_disabled(app) {
+        return Err(SidecarRequestError::Disabled);
+    }
+    sidecar::ensure_sidecar_started(app, name).await.map_err(|_| SidecarRequestError::NotRunning)
+}
+
+#[tauri::command]
+pub async fn sidecar_request(
+    app: AppHandle,
+    method: String,
+    path: String,
+    body: Option<String>,
+    headers: Option<HashMap<String, String>>,
+    name: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<SidecarResponse, SidecarRequestError> {
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    let timeout = request_timeout(timeout_ms);
+    let addr = running_addr(&app, name).await?;
+    let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes())
+        .map_err(|_| SidecarRequestError::InvalidRequest(format!("unknown method {}", method)))?;
+    if !path.starts_with('/') {
+        return Err(SidecarRequestError::InvalidRequest("path must start with '/'".to_string()));
+    }
+
+    let mut request = app
+        .state::<SidecarHttp>()
+        .0
+        .request(method, format!("http://{}{}", addr, path))
+        .timeout(timeout)
+        .bearer_auth(&app.state::<SidecarToken>().0);
+    for (key, value) in headers.unwrap_or_default() {
+        // The token is ours to set
+        if !key.eq_ignore_ascii_case("authorization") {
+            request = request.header(key, value);
+        }
+    }
+    if let Some(body) = body {
+        request = request.body(body);
+    }
+    send(request, timeout).await
+}
+
+// Send a proxied request and read the whole response. `timeout` is the one
+// the request was built with, for the error.
+async fn send(request: reqwest::RequestBuilder, timeout: Duration) -> Result<SidecarResponse, SidecarRequestError> {
+    let response = request.send().await.map_err(|e| SidecarRequestError::from_reqwest(e, timeout))?;
+    let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let body = response.bytes().await.map_err(|e| SidecarRequestError::from_reqwest(e, timeout))?;
+    Ok(SidecarResponse {
+        status,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+// The version the sidecar reports on GET /version. A version that isn't
+// compatible with the app's also emits `version-mismatch`, which the UI
+// shows as a warning.
+#[tauri::command]
+pub async fn get_sidecar_version(
+    app: AppHandle,
+    http: State<'_, SidecarHttp>,
+    name: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<String, SidecarRequestError> {
+    let name = name.as_deref().unwrap_or(DEFAULT_SIDECAR);
+    let timeout = request_timeout(timeout_ms);
+    let addr = running_addr(&app, name).await?;
+    let response = http
+        .0
+        .get(format!("http://{}/version", addr))
+        .timeout(timeout)
+        .bearer_auth(&app.state::<SidecarToken>().0)
+        .send()
+        .await
+        .map_err(|e| SidecarRequestError::from_reqwest(e, timeout))?;
+    if !response.status().is_success() {
+        return Err(SidecarRequestError::Failed(format!("GET /version returned {}", response.status())));
+    }
+    let body = response.bytes().await.map_err(|e| SidecarRequestError::from_reqwest(e, timeout))?;
+    let version = serde_json::from_slice::<VersionResponse>(&body)
+        .map_err(|e| SidecarRequestError::Failed(format!("invalid /version response: {}", e)))?
+        .version;
+
+    let app_version = &app.package_info().version;
+    match semver::Version::parse(&version) {
+        Ok(sidecar_version) if compatible(app_version, &sidecar_version) => {}
+        Ok(_) => {
+            tracing::warn!(sidecar = name, %app_version, sidecar_version = %version, "Sidecar version is incompatible");
+            events::emit(&app, AppEvent::VersionMismatch(VersionMismatch {
+                app_version: app_version.to_string(),
+                sidecar_version: version.clone(),
+            }));
+        }
+        Err(e) => tracing::warn!(sidecar = name, "Sidecar reported an invalid version {:?}: {}", version, e),
+    }
+    Ok(version)
+}
+
+// Same major version, and the same minor while still at 0.x, where minor
+// releases may break the API
+fn compatible(app: &semver::Version, sidecar: &semver::Version) -> bool {
+    app.major == sidecar.major && (app.major > 0 || app.minor == sidecar.minor)
+}
+
+// Wait for the backend to finish the requests it has in flight, per GET
+// /busy. True once it's idle, false if it's still busy after `timeout`. A
+// backend that isn't running, can't be reached or predates /busy has
+// nothing we could protect, so it counts as idle.
+pub async fn wait_until_idle(app: &AppHandle, timeout: Duration) -> bool {
+    let Some(addr) = sidecar::addr_of(app, DEFAULT_SIDECAR) else {
+        return true;
+    };
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        match is_busy(app, addr).await {
+            Ok(false) => return true,
+            Ok(true) => {}
+            Err(e) => {
+                tracing::info!("Not waiting for the sidecar to go idle: {}", e);
+                return true;
+            }
+        }
+        if tokio::time::Instant::now() + BUSY_POLL_INTERVAL >= deadline {
+            return false;
+        }
+        tokio::time::sleep(BUSY_POLL_INTERVAL).await;
+    }
+}
+
+async fn is_busy(app: &AppHandle, addr: SocketAddr) -> Result<bool, String> {
+    let response = app
+        .state::<SidecarHttp>()
+        .0
+        .get(format!("http://{}/busy", addr))
+        .timeout(BUSY_REQUEST_TIMEOUT)
+        .bearer_auth(&app.state::<SidecarToken>().0)
+        .send()
+        .await
+        .map_err(|e| SidecarRequestError::from_reqwest(e, BUSY_REQUEST_TIMEOUT).to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("GET /busy returned {}", response.status()));
+    }
+    let body = response.bytes().await.map_err(|e| e.to_string())?;
+    serde_json::from_slice::<BusyResponse>(&body)
+        .map(|response| response.busy)
+        .map_err(|e| format!("invalid /busy response: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unresponsive_sidecar_times_out() {
+        // Connections are accepted into the backlog but never answered
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let timeout = request_timeout(Some(200));
+        let request = reqwest::Client::new().get(format!("http://{}/health", addr)).timeout(timeout);
+
+        let result = tauri::async_runtime::block_on(send(request, timeout));
+        assert!(matches!(result, Err(SidecarRequestError::Timeout(200))), "{:?}", result);
+        drop(listener);
+    }
+}
@@ -0,0 +1,46 @@
+// Startup splash window
+//
+// The backend takes a few seconds to load its model, so the main window
+// stays hidden behind a small splash until the default sidecar reports its
+// port. splash.html shows the error and a retry button if startup fails.
+
+use crate::sidecar::DEFAULT_SIDECAR;
+use tauri::{AppHandle, Listener, Manager, WebviewUrl, WebviewWindowBuilder};
+
+pub const SPLASH_WINDOW: &str = "splash";
+
+pub fn show_splash(app: &AppHandle) -> tauri::Result<()> {
+    WebviewWindowBuilder::new(app, SPLASH_WINDOW, WebviewUrl::App("splash.html".into()))
+        .title("Retention")
+        .inner_size(420.0, 280.0)
+        .resizable(false)
+        .decorations(false)
+        .center()
+        .build()?;
+
+    let handle = app.clone();
+    app.listen_any("sidecar-ready", move |event| {
+        let name = serde_json::from_str::<serde_json::Value>(event.payload())
+            .ok()
+            .and_then(|payload| payload.get("name")?.as_str().map(str::to_string));
+        if name.as_deref() == Some(DEFAULT_SIDECAR) {
+            finish(&handle);
+        }
+    });
+    Ok(())
+}
+
+// Swap the splash for the main window. Does nothing once the splash is gone,
+// so later restarts don't re-show a window the user hid to the tray.
+fn finish(app: &AppHandle) {
+    let Some(splash) = app.get_webview_window(SPLASH_WINDOW) else {
+        return;
+    };
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    // destroy() rather than close() so this isn't mistaken for the user
+    // closing the splash
+    let _ = splash.destroy();
+}
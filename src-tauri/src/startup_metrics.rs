@@ -0,0 +1,79 @@
+// Backend cold-start timings
+//
+// How long the backend took from spawn to SIDECAR_READY=1 on its last few
+// starts, so the splash can estimate how long it'll take this time. They're
+// measured, not set, but live in config.json (`sidecar.startup_metrics`)
+// so the estimate survives a restart. Timings from another app version say
+// little about this one, so a version change starts the history over.
+
+use crate::config;
+use crate::lock::LockExt;
+use crate::safe_mode::SafeMode;
+use crate::sidecar::SidecarConfig;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+// How many starts the average covers
+const HISTORY_LEN: usize = 10;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StartupMetrics {
+    // Version the timings were taken with
+    pub app_version: Option<String>,
+    // Oldest first
+    pub startup_ms: Vec<u64>,
+}
+
+// Result of get_startup_metrics; the averages are None without history
+#[derive(Clone, Serialize)]
+pub struct StartupMetricsReport {
+    startup_ms: Vec<u64>,
+    last_ms: Option<u64>,
+    average_ms: Option<u64>,
+}
+
+// Called each time the backend reports ready
+pub fn record(app: &AppHandle, startup_ms: u64) {
+    // Safe mode runs on the default config; saving it would overwrite the
+    // user's real settings
+    if app.state::<SafeMode>().0 {
+        return;
+    }
+    let version = app.package_info().version.to_string();
+    {
+        let config = app.state::<Mutex<SidecarConfig>>();
+        let mut config = config.lock_or_recover();
+        let metrics = &mut config.startup_metrics;
+        if metrics.app_version.as_deref() != Some(version.as_str()) {
+            *metrics = StartupMetrics {
+                app_version: Some(version),
+                startup_ms: Vec::new(),
+            };
+        }
+        metrics.startup_ms.push(startup_ms);
+        let excess = metrics.startup_ms.len().saturating_sub(HISTORY_LEN);
+        metrics.startup_ms.drain(..excess);
+    }
+    if let Err(e) = config::persist(app) {
+        tracing::warn!("Failed to save startup metrics: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn get_startup_metrics(app: AppHandle, config: State<Mutex<SidecarConfig>>) -> StartupMetricsReport {
+    let version = app.package_info().version.to_string();
+    let metrics = config.lock_or_recover().startup_metrics.clone();
+    let startup_ms = if metrics.app_version.as_deref() == Some(version.as_str()) {
+        metrics.startup_ms
+    } else {
+        Vec::new()
+    };
+    let average_ms = (!startup_ms.is_empty()).then(|| startup_ms.iter().sum::<u64>() / startup_ms.len() as u64);
+    StartupMetricsReport {
+        last_ms: startup_ms.last().copied(),
+        average_ms,
+        startup_ms,
+    }
+}
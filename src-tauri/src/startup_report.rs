@@ -0,0 +1,119 @@
+// The "app is ready" signal
+//
+// `startup-complete` is sent once per launch, the first time the built-in
+// backend is serving, with what the UI and smoke tests want to know about
+// the run in one place. Until then, each failed attempt to start the
+// backend sends `startup-failed` with whatever was known at that point.
+// Both are timed from app launch, unlike `sidecar-ready`, which is timed
+// from the backend's spawn.
+
+use crate::events::{self, AppEvent};
+use crate::lock::LockExt;
+use crate::sidecar::{self, SidecarInfo};
+use crate::sidecar_http;
+use crate::updater::{UpdateChannel, UpdaterSettings};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+use tokio::sync::watch;
+
+pub struct StartupReport {
+    started: Instant,
+    completed: AtomicBool,
+    // The latest `startup-failed` error, for headless runs to wait on
+    failure: watch::Sender<Option<String>>,
+}
+
+impl StartupReport {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            completed: AtomicBool::new(false),
+            failure: watch::channel(None).0,
+        }
+    }
+}
+
+// Payload of `startup-complete`. `sidecar_version` is None if the backend
+// didn't answer GET /version.
+#[derive(Clone, Serialize)]
+pub struct StartupComplete {
+    port: u16,
+    startup_ms: u64,
+    sidecar_version: Option<String>,
+    model: Option<String>,
+    channel: UpdateChannel,
+}
+
+// Payload of `startup-failed`
+#[derive(Clone, Serialize)]
+pub struct StartupFailed {
+    error: String,
+    startup_ms: u64,
+    port: Option<u16>,
+    model: Option<String>,
+    channel: UpdateChannel,
+}
+
+// Called when the built-in backend reports ready. The version is asked for
+// afterwards, so the event follows `sidecar-ready` by a request.
+pub fn complete(app: &AppHandle, port: u16) {
+    let report = app.state::<StartupReport>();
+    if report.completed.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let startup_ms = report.started.elapsed().as_millis() as u64;
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let sidecar_version = sidecar_http::get_sidecar_version(app.clone(), app.state(), None, None)
+            .await
+            .inspect_err(|e| tracing::warn!("Failed to get the sidecar version: {}", e))
+            .ok();
+        let complete = StartupComplete {
+            port,
+            startup_ms,
+            sidecar_version,
+            model: announced_model(&app),
+            channel: channel(&app),
+        };
+        tracing::info!(port, startup_ms, version = ?complete.sidecar_version, "Startup complete");
+        events::emit(&app, AppEvent::StartupComplete(complete));
+    });
+}
+
+// Called when the built-in backend fails to start. Nothing is sent once
+// startup has completed; later failures are restarts, not startup.
+pub fn failed(app: &AppHandle, error: &str) {
+    let report = app.state::<StartupReport>();
+    if report.completed.load(Ordering::SeqCst) {
+        return;
+    }
+    report.failure.send_replace(Some(error.to_string()));
+    events::emit(app, AppEvent::StartupFailed(StartupFailed {
+        error: error.to_string(),
+        startup_ms: report.started.elapsed().as_millis() as u64,
+        port: sidecar::addr_of(app, sidecar::DEFAULT_SIDECAR).map(|addr| addr.port()),
+        model: announced_model(app),
+        channel: channel(app),
+    }));
+}
+
+// Startup failures as they happen; holds None until the first
+pub fn failures(app: &AppHandle) -> watch::Receiver<Option<String>> {
+    app.state::<StartupReport>().failure.subscribe()
+}
+
+fn announced_model(app: &AppHandle) -> Option<String> {
+    app.state::<SidecarInfo>()
+        .0
+        .lock_or_recover()
+        .get(sidecar::DEFAULT_SIDECAR)?
+        .get("model")
+        .cloned()
+}
+
+fn channel(app: &AppHandle) -> UpdateChannel {
+    app.state::<Mutex<UpdaterSettings>>().lock_or_recover().channel
+}
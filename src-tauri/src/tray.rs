@@ -0,0 +1,81 @@
+// System tray icon
+//
+// Lets the window be hidden while the sidecar keeps running. Clicking the
+// icon toggles the main window; the menu can also restart the backend or
+// quit the app.
+
+use crate::sidecar;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Manager};
+
+const SHOW_WINDOW: &str = "show-window";
+const RESTART_BACKEND: &str = "restart-backend";
+const QUIT: &str = "quit";
+
+pub fn create_tray(app: &AppHandle) -> tauri::Result<()> {
+    let menu = Menu::with_items(app, &[
+        &MenuItem::with_id(app, SHOW_WINDOW, "Show Window", true, None::<&str>)?,
+        &MenuItem::with_id(app, RESTART_BACKEND, "Restart Backend", true, None::<&str>)?,
+        &PredefinedMenuItem::separator(app)?,
+        &MenuItem::with_id(app, QUIT, "Quit", true, None::<&str>)?,
+    ])?;
+
+    let mut tray = TrayIconBuilder::with_id("main")
+        .tooltip("Flash-AI")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(on_menu_event)
+        .on_tray_icon_event(on_tray_icon_event);
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+    Ok(())
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        SHOW_WINDOW => show_main_window(app),
+        RESTART_BACKEND => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = sidecar::restart_sidecar(app, None).await {
+                    tracing::error!("Failed to restart sidecar from the tray: {}", e);
+                }
+            });
+        }
+        QUIT => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move { crate::quit(&app).await });
+        }
+        _ => {}
+    }
+}
+
+fn on_tray_icon_event(tray: &TrayIcon, event: TrayIconEvent) {
+    if let TrayIconEvent::Click {
+        button: MouseButton::Left,
+        button_state: MouseButtonState::Up,
+        ..
+    } = event
+    {
+        let app = tray.app_handle();
+        let Some(window) = app.get_webview_window("main") else {
+            return;
+        };
+        if window.is_visible().unwrap_or(false) {
+            let _ = window.hide();
+        } else {
+            show_main_window(app);
+        }
+    }
+}
+
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
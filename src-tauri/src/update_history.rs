@@ -0,0 +1,97 @@
+// Record of installed updates
+//
+// Every successful install adds an entry to `update-history.json` in the
+// app config dir, so support can line a change in behavior up with the
+// update that brought it. The file keeps the newest HISTORY_LEN entries,
+// oldest first; get_update_history returns them newest first.
+
+use crate::updater::UpdateChannel;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+const HISTORY_FILE: &str = "update-history.json";
+const HISTORY_LEN: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateHistoryEntry {
+    from_version: String,
+    to_version: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    channel: UpdateChannel,
+}
+
+// Called once an update has been installed, before any relaunch. On Windows
+// a successful install exits the app, so it's called just before installing
+// instead, and `unrecord` takes the entry back if the install fails.
+pub fn record(app: &AppHandle, to_version: &str, channel: UpdateChannel) {
+    let path = match history_path(app) {
+        Ok(path) => path,
+        Err(e) => {
+            tracing::warn!("Failed to record the update: {}", e);
+            return;
+        }
+    };
+    let mut history = read(&path);
+    history.push(UpdateHistoryEntry {
+        from_version: app.package_info().version.to_string(),
+        to_version: to_version.to_string(),
+        timestamp: chrono::Utc::now(),
+        channel,
+    });
+    let excess = history.len().saturating_sub(HISTORY_LEN);
+    history.drain(..excess);
+    write(&path, &history);
+}
+
+// Drop the newest entry if it's the one `record` just added for `to_version`
+pub fn unrecord(app: &AppHandle, to_version: &str) {
+    let Ok(path) = history_path(app) else {
+        return;
+    };
+    let mut history = read(&path);
+    if history.last().is_some_and(|entry| entry.to_version == to_version) {
+        history.pop();
+        write(&path, &history);
+    }
+}
+
+// Installed updates, newest first
+#[tauri::command]
+pub fn get_update_history(app: AppHandle) -> Result<Vec<UpdateHistoryEntry>, String> {
+    let mut history = read(&history_path(&app)?);
+    history.reverse();
+    Ok(history)
+}
+
+fn history_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(HISTORY_FILE))
+        .map_err(|e| format!("Failed to resolve the app config dir: {}", e))
+}
+
+fn write(path: &Path, history: &[UpdateHistoryEntry]) {
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(path, serde_json::to_string_pretty(history).unwrap_or_default()));
+    if let Err(e) = result {
+        tracing::warn!("Failed to write {}: {}", path.display(), e);
+    }
+}
+
+// Oldest first; empty if there's no history yet or it can't be read
+fn read(path: &Path) -> Vec<UpdateHistoryEntry> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring invalid {}: {}", path.display(), e);
+            Vec::new()
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            tracing::warn!("Failed to read {}: {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,1270 @@
+// App update commands
+//
+// Thin wrappers around tauri-plugin-updater. The feed URL depends on the
+// selected update channel, which is persisted with the rest of the config.
+//
+// Updates are signed with the minisign key whose public half is pinned as
+// `plugins > updater > pubkey` in tauri.conf.json, so it's compiled into
+// the app and can't be changed without a new release. The plugin checks
+// every download against it; verify_update_signature repeats that check on
+// the saved artifact for audits.
+
+use crate::config;
+use crate::events::{self, AppEvent};
+use crate::lock::LockExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State, Url};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_updater::{Update, Updater, UpdaterExt};
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+// Pre-releases are published under a rolling `beta` release tag
+const BETA_UPDATE_ENDPOINT: &str =
+    "https://github.com/ZuhaadRathore/Retention/releases/download/beta/latest.json";
+const UPDATER_SETTINGS_FILE: &str = "updater.json";
+// Give the sidecar a head start before hitting the network
+const STARTUP_CHECK_DELAY: Duration = Duration::from_secs(10);
+const DEFAULT_REMIND_LATER_HOURS: u32 = 24;
+const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30;
+// Fast connections deliver many chunks per frame; don't flood the event
+// channel. Progress is sent when both the interval has passed and the
+// percentage has moved by the step, and always at 100%.
+const PROGRESS_EMIT_INTERVAL: Duration = Duration::from_millis(100);
+const PROGRESS_EMIT_STEP_PERCENT: f64 = 1.0;
+// Download rate is averaged over roughly this much recent progress
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+const ARTIFACT_SIZE_TIMEOUT: Duration = Duration::from_secs(5);
+const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(10);
+// Downloaded updates waiting to be installed, under the app cache dir
+const UPDATES_DIR: &str = "updates";
+// Free space wanted on top of twice the download size (the artifact, and
+// about as much again for the installer to unpack it)
+const DISK_SPACE_MARGIN_BYTES: u64 = 100 * BYTES_PER_MB;
+const BYTES_PER_MB: u64 = 1024 * 1024;
+// Artifacts left behind this long are removed at startup
+const STALE_ARTIFACT_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+// Overrides the feed URL of every channel, e.g. for a self-hosted mirror
+const UPDATE_URL_ENV_VAR: &str = "FLASH_AI_UPDATE_URL";
+// Query parameter appended to the feed URL on forced checks
+const CACHE_BUST_PARAM: &str = "nocache";
+
+// Error type of the update commands. Serialized as
+// `{ "kind": "checkFailed", "message": "..." }` so the UI can branch on
+// `kind` and show `message` as-is.
+#[derive(Debug, Clone)]
+pub enum UpdateError {
+    UpdaterDisabled,
+    UpdaterUnavailable(String),
+    CheckFailed(String),
+    NoUpdate,
+    DownloadInProgress,
+    NoDownloadInProgress,
+    Cancelled,
+    DownloadFailed(String),
+    // The downloaded artifact's signature didn't check out
+    VerificationFailed(String),
+    DiskFull(String),
+    // Found before downloading: the update needs `required` bytes free
+    // where it's saved and only `available` are
+    InsufficientDiskSpace { required: u64, available: u64 },
+    NotDownloaded,
+    ChecksumMismatch { expected: String, actual: String },
+    InstallFailed(String),
+    // The backend still had work in flight when drain_timeout_secs ran
+    // out; installing with `force` skips the wait
+    SidecarBusy,
+    // Connectivity check failures
+    NoEndpoint,
+    DnsFailed(String),
+    ConnectionRefused(String),
+    HttpStatus(u16),
+    Unreachable(String),
+}
+
+impl UpdateError {
+    fn kind(&self) -> &'static str {
+        match self {
+            UpdateError::UpdaterDisabled => "updaterDisabled",
+            UpdateError::UpdaterUnavailable(_) => "updaterUnavailable",
+            UpdateError::CheckFailed(_) => "checkFailed",
+            UpdateError::NoUpdate => "noUpdate",
+            UpdateError::DownloadInProgress => "downloadInProgress",
+            UpdateError::NoDownloadInProgress => "noDownloadInProgress",
+            UpdateError::Cancelled => "cancelled",
+            UpdateError::DownloadFailed(_) => "downloadFailed",
+            UpdateError::VerificationFailed(_) => "verificationFailed",
+            UpdateError::DiskFull(_) => "diskFull",
+            UpdateError::InsufficientDiskSpace { .. } => "insufficientDiskSpace",
+            UpdateError::NotDownloaded => "notDownloaded",
+            UpdateError::ChecksumMismatch { .. } => "checksumMismatch",
+            UpdateError::InstallFailed(_) => "installFailed",
+            UpdateError::SidecarBusy => "sidecarBusy",
+            UpdateError::NoEndpoint => "noEndpoint",
+            UpdateError::DnsFailed(_) => "dnsFailed",
+            UpdateError::ConnectionRefused(_) => "connectionRefused",
+            UpdateError::HttpStatus(_) => "httpStatus",
+            UpdateError::Unreachable(_) => "unreachable",
+        }
+    }
+
+    // Which step of an update failed, for `update-error`. None for errors
+    // that aren't failures, like NoUpdate or Cancelled.
+    fn stage(&self) -> Option<&'static str> {
+        match self {
+            UpdateError::UpdaterUnavailable(_)
+            | UpdateError::CheckFailed(_)
+            | UpdateError::NoEndpoint
+            | UpdateError::DnsFailed(_)
+            | UpdateError::ConnectionRefused(_)
+            | UpdateError::HttpStatus(_)
+            | UpdateError::Unreachable(_) => Some("check"),
+            UpdateError::DownloadFailed(_) | UpdateError::DiskFull(_) | UpdateError::InsufficientDiskSpace { .. } => {
+                Some("download")
+            }
+            UpdateError::VerificationFailed(_) | UpdateError::ChecksumMismatch { .. } => Some("verify"),
+            UpdateError::InstallFailed(_) => Some("install"),
+            _ => None,
+        }
+    }
+
+    // Whether trying again as-is might work: network trouble, yes; a bad
+    // signature or a full disk, no
+    fn retryable(&self) -> bool {
+        match self {
+            UpdateError::CheckFailed(_)
+            | UpdateError::DnsFailed(_)
+            | UpdateError::ConnectionRefused(_)
+            | UpdateError::Unreachable(_)
+            | UpdateError::DownloadFailed(_) => true,
+            UpdateError::HttpStatus(status) => *status >= 500 || *status == 429,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateError::UpdaterDisabled => write!(f, "Updates are disabled"),
+            UpdateError::UpdaterUnavailable(e) => write!(f, "Failed to get updater: {}", e),
+            UpdateError::CheckFailed(e) => write!(f, "Failed to check for updates: {}", e),
+            UpdateError::NoUpdate => write!(f, "No updates available"),
+            UpdateError::DownloadInProgress => write!(f, "An update download is already in progress"),
+            UpdateError::NoDownloadInProgress => write!(f, "No update download in progress"),
+            UpdateError::Cancelled => write!(f, "Update cancelled"),
+            UpdateError::DownloadFailed(e) => write!(f, "Failed to download update: {}", e),
+            UpdateError::VerificationFailed(e) => write!(f, "The update's signature is invalid: {}", e),
+            UpdateError::DiskFull(e) => write!(f, "Not enough disk space for the update: {}", e),
+            UpdateError::InsufficientDiskSpace { required, available } => write!(
+                f,
+                "Not enough disk space for the update: {} MB needed, {} MB free",
+                required.div_ceil(BYTES_PER_MB),
+                available / BYTES_PER_MB
+            ),
+            UpdateError::NotDownloaded => write!(f, "No downloaded update to install"),
+            UpdateError::ChecksumMismatch { expected, actual } => {
+                write!(f, "checksum mismatch: expected {}, got {}", expected, actual)
+            }
+            UpdateError::InstallFailed(e) => write!(f, "Failed to install update: {}", e),
+            UpdateError::SidecarBusy => write!(f, "The backend is still busy; install anyway or try again later"),
+            UpdateError::NoEndpoint => write!(f, "No update server is configured"),
+            UpdateError::DnsFailed(host) => write!(f, "Could not resolve the update server {}", host),
+            UpdateError::ConnectionRefused(host) => write!(f, "The update server {} refused the connection", host),
+            UpdateError::HttpStatus(status) => write!(f, "The update server returned HTTP {}", status),
+            UpdateError::Unreachable(e) => write!(f, "The update server is unreachable: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for UpdateError {}
+
+impl Serialize for UpdateError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("UpdateError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+// Payload of `update-error`, sent when downloading or installing fails so
+// the UI can decide whether to offer a retry. `stage` is "check",
+// "download", "verify" or "install"; `kind` matches UpdateError's.
+#[derive(Clone, Serialize)]
+pub struct UpdateFailure {
+    stage: &'static str,
+    kind: &'static str,
+    message: String,
+    retryable: bool,
+}
+
+// Payload of `update-drain-timeout`: the backend didn't finish its work
+// in time, so the UI should ask whether to install anyway (apply_update
+// with `force`)
+#[derive(Clone, Serialize)]
+pub struct UpdateDrainTimeout {
+    version: String,
+    waited_secs: u64,
+}
+
+// Release metadata for the UI's changelog dialog
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub release_notes: Option<String>,
+    // RFC 3339, as published in the update manifest
+    pub pub_date: Option<String>,
+    // Taken from the artifact's Content-Length; None if the server omits it
+    pub download_size: Option<u64>,
+}
+
+impl UpdateInfo {
+    async fn from_update(update: &Update) -> Self {
+        Self {
+            version: update.version.clone(),
+            current_version: update.current_version.clone(),
+            release_notes: update.body.clone(),
+            pub_date: update
+                .raw_json
+                .get("pub_date")
+                .and_then(|date| date.as_str())
+                .map(str::to_string),
+            download_size: artifact_size(&update.download_url).await,
+        }
+    }
+}
+
+// Payload of `update-progress`. Replaces the bare percentage sent before;
+// `percent`, `total_bytes` and `eta_secs` are None when the server sends no
+// length. The rate is None until there are two samples to compare.
+#[derive(Clone, Serialize)]
+pub struct UpdateProgress {
+    downloaded_bytes: u64,
+    total_bytes: Option<u64>,
+    percent: Option<f64>,
+    bytes_per_second: Option<f64>,
+    eta_secs: Option<u64>,
+}
+
+// Payload of `update-download-complete`: the artifact is saved and ready
+// to install
+#[derive(Clone, Serialize)]
+pub struct UpdateDownloadComplete {
+    version: String,
+    bytes: u64,
+}
+
+// Payload of `update-install-complete`. With `relaunch` the app restarts
+// into `version` right away; otherwise the UI should ask for a restart.
+#[derive(Clone, Serialize)]
+pub struct UpdateInstallComplete {
+    version: String,
+    relaunch: bool,
+}
+
+// An update fetched by download_update and not yet installed
+pub struct DownloadedUpdate {
+    update: Update,
+    path: PathBuf,
+}
+
+pub struct PendingUpdate(pub Mutex<Option<DownloadedUpdate>>);
+
+// Held for the duration of an update check so background and manual checks
+// never run at the same time
+pub struct UpdateCheckLock(pub tokio::sync::Mutex<()>);
+
+// Signals the download in progress to stop; None when nothing is downloading
+pub struct UpdateCancel(pub Mutex<Option<oneshot::Sender<()>>>);
+
+pub struct UpdatePoller(pub Mutex<Option<tauri::async_runtime::JoinHandle<()>>>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl std::fmt::Display for UpdateChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateChannel::Stable => write!(f, "stable"),
+            UpdateChannel::Beta => write!(f, "beta"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdaterSettings {
+    // Off for managed deployments that ship updates through their own
+    // tooling. The updater plugin is only registered when this is set at
+    // launch; every update command fails with `updaterDisabled` otherwise.
+    pub updater_enabled: bool,
+    pub channel: UpdateChannel,
+    // Check once shortly after launch and emit `update-available`
+    pub auto_check_updates: bool,
+    // Set by remind_me_later; automatic checks are skipped until then
+    pub remind_after: Option<chrono::DateTime<chrono::Utc>>,
+    // Set by skip_update_version; checks don't report this version, and a
+    // newer release clears it
+    pub skipped_version: Option<String>,
+    // Background re-check period; 0 disables it
+    pub update_check_interval_hours: u32,
+    // Feed URL replacing the channel's own; FLASH_AI_UPDATE_URL overrides it
+    pub update_url: Option<String>,
+    // How long an install waits for the backend to finish in-flight
+    // requests; 0 installs right away
+    pub drain_timeout_secs: u64,
+}
+
+impl Default for UpdaterSettings {
+    fn default() -> Self {
+        Self {
+            updater_enabled: true,
+            channel: UpdateChannel::default(),
+            auto_check_updates: true,
+            remind_after: None,
+            skipped_version: None,
+            update_check_interval_hours: 6,
+            update_url: None,
+            drain_timeout_secs: DEFAULT_DRAIN_TIMEOUT_SECS,
+        }
+    }
+}
+
+impl UpdaterSettings {
+    // updater.json predates config.json and is only read as a fallback
+    pub fn load(app: &AppHandle) -> Self {
+        let Ok(dir) = app.path().app_config_dir() else {
+            return Self::default();
+        };
+        let path = dir.join(UPDATER_SETTINGS_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str(&contents) {
+                Ok(section) => config::lenient(&path, section, |_| Vec::new()),
+                Err(e) => {
+                    warn!("Ignoring invalid {}: {}", path.display(), e);
+                    Self::default()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                warn!("Failed to read {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+}
+
+// Build an updater for the given channel. Stable uses the endpoints from
+// tauri.conf.json; other channels swap in their own feed, and a custom
+// endpoint replaces either. A forced check
+// sends no-cache headers and tags the feed URL with a unique query
+// parameter, so neither the CDN nor an HTTP cache can answer with a stale
+// manifest.
+fn channel_updater(app: &AppHandle, channel: UpdateChannel, force: bool) -> Result<Updater, tauri_plugin_updater::Error> {
+    let custom = custom_endpoint(app);
+    let mut endpoints = match (custom, channel) {
+        (Some(endpoint), _) => vec![endpoint],
+        (None, UpdateChannel::Stable) if !force => return app.updater(),
+        (None, UpdateChannel::Stable) => configured_endpoints(app),
+        (None, UpdateChannel::Beta) => vec![Url::parse(BETA_UPDATE_ENDPOINT)?],
+    };
+    let mut builder = app.updater_builder();
+    if force {
+        let stamp = chrono::Utc::now().timestamp_millis().to_string();
+        for endpoint in &mut endpoints {
+            endpoint.query_pairs_mut().append_pair(CACHE_BUST_PARAM, &stamp);
+        }
+        builder = builder
+            .header("Cache-Control", "no-cache")?
+            .header("Pragma", "no-cache")?;
+    }
+    if !endpoints.is_empty() {
+        builder = builder.endpoints(endpoints)?;
+    }
+    builder.build()
+}
+
+// The stable feed as listed under plugins > updater in tauri.conf.json
+fn configured_endpoints(app: &AppHandle) -> Vec<Url> {
+    app.config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("endpoints"))
+        .and_then(|endpoints| serde_json::from_value(endpoints.clone()).ok())
+        .unwrap_or_default()
+}
+
+// The pinned key as written in tauri.conf.json: base64 of the minisign
+// public key file
+fn pinned_pubkey(app: &AppHandle) -> Option<String> {
+    app.config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("pubkey"))
+        .and_then(|pubkey| pubkey.as_str())
+        .map(str::to_string)
+}
+
+// FLASH_AI_UPDATE_URL, or else `update_url` from the config, for
+// self-hosted mirrors. An invalid URL is ignored with a warning.
+fn custom_endpoint(app: &AppHandle) -> Option<Url> {
+    let (source, raw) = match std::env::var(UPDATE_URL_ENV_VAR).ok().filter(|url| !url.is_empty()) {
+        Some(url) => (UPDATE_URL_ENV_VAR, url),
+        None => ("update_url", app.state::<Mutex<UpdaterSettings>>().lock_or_recover().update_url.clone()?),
+    };
+    match Url::parse(&raw) {
+        Ok(url) => Some(url),
+        Err(e) => {
+            warn!("Ignoring invalid {} {:?}: {}", source, raw, e);
+            None
+        }
+    }
+}
+
+// The feed the current channel is checked against
+fn effective_endpoint(app: &AppHandle) -> Option<Url> {
+    custom_endpoint(app).or_else(|| match current_channel(app) {
+        UpdateChannel::Stable => configured_endpoints(app).into_iter().next(),
+        UpdateChannel::Beta => Url::parse(BETA_UPDATE_ENDPOINT).ok(),
+    })
+}
+
+#[tauri::command]
+pub fn get_update_endpoint(app: AppHandle) -> Option<String> {
+    effective_endpoint(&app).map(String::from)
+}
+
+fn updater_enabled(app: &AppHandle) -> bool {
+    app.state::<Mutex<UpdaterSettings>>().lock_or_recover().updater_enabled
+}
+
+fn ensure_enabled(app: &AppHandle) -> Result<(), UpdateError> {
+    if updater_enabled(app) {
+        Ok(())
+    } else {
+        Err(UpdateError::UpdaterDisabled)
+    }
+}
+
+fn current_channel(app: &AppHandle) -> UpdateChannel {
+    app.state::<Mutex<UpdaterSettings>>().lock_or_recover().channel
+}
+
+#[tauri::command]
+pub fn get_update_channel(settings: State<Mutex<UpdaterSettings>>) -> UpdateChannel {
+    settings.lock_or_recover().channel
+}
+
+// Takes effect on the next check; no restart needed
+#[tauri::command]
+pub fn set_update_channel(
+    app: AppHandle,
+    settings: State<Mutex<UpdaterSettings>>,
+    channel: UpdateChannel,
+) -> Result<(), String> {
+    settings.lock_or_recover().channel = channel;
+    config::persist(&app)
+}
+
+// `force` bypasses any cached copy of the update feed, e.g. right after
+// publishing a release. A version the user skipped counts as no update.
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle, force: Option<bool>) -> Result<String, UpdateError> {
+    let channel = current_channel(&app);
+    let update = check_update(&app, force.unwrap_or(false)).await?;
+    match update.filter(|update| !is_skipped(&app, update)) {
+        Some(update) => Ok(format!("Update available: v{} ({} channel)", update.version, channel)),
+        None => Ok(format!("No updates available ({} channel)", channel)),
+    }
+}
+
+// Structured variant of check_for_updates; None when up to date
+#[tauri::command]
+pub async fn get_available_update(app: AppHandle, force: Option<bool>) -> Result<Option<UpdateInfo>, UpdateError> {
+    let update = check_update(&app, force.unwrap_or(false)).await?;
+    match update.filter(|update| !is_skipped(&app, update)) {
+        Some(update) => Ok(Some(UpdateInfo::from_update(&update).await)),
+        None => Ok(None),
+    }
+}
+
+// get_available_update for the "Check now" button: bypasses the feed cache
+// and reports the latest version even if the user skipped it
+#[tauri::command]
+pub async fn force_update_check(app: AppHandle) -> Result<Option<UpdateInfo>, UpdateError> {
+    match check_update(&app, true).await? {
+        Some(update) => Ok(Some(UpdateInfo::from_update(&update).await)),
+        None => Ok(None),
+    }
+}
+
+// Stop offering `version`, e.g. "1.4.0", until a newer one is released
+#[tauri::command]
+pub fn skip_update_version(
+    app: AppHandle,
+    settings: State<Mutex<UpdaterSettings>>,
+    version: String,
+) -> Result<(), String> {
+    let version = version.trim();
+    let version = version.strip_prefix('v').unwrap_or(version);
+    if version.is_empty() {
+        return Err("version must not be empty".to_string());
+    }
+    info!(version, "Skipping update version");
+    settings.lock_or_recover().skipped_version = Some(version.to_string());
+    config::persist(&app)
+}
+
+// Whether `update` is the version the user skipped. Seeing a newer one
+// clears the skip.
+fn is_skipped(app: &AppHandle, update: &Update) -> bool {
+    let Some(skipped) = app.state::<Mutex<UpdaterSettings>>().lock_or_recover().skipped_version.clone() else {
+        return false;
+    };
+    if update.version == skipped {
+        info!(version = %skipped, "Not reporting the skipped update");
+        return true;
+    }
+    // A version we can't compare is treated as newer, so the skip can't
+    // hide updates forever
+    let newer = match (semver::Version::parse(&update.version), semver::Version::parse(&skipped)) {
+        (Ok(version), Ok(skipped)) => version > skipped,
+        _ => true,
+    };
+    if newer {
+        info!(skipped = %skipped, version = %update.version, "Newer update than the skipped one, clearing the skip");
+        app.state::<Mutex<UpdaterSettings>>().lock_or_recover().skipped_version = None;
+        if let Err(e) = config::persist(app) {
+            warn!("Failed to save the config: {}", e);
+        }
+    }
+    false
+}
+
+// Fetch the current channel's feed to find out whether the update server
+// can be reached at all, e.g. from behind a corporate proxy. The error kind
+// tells DNS failures, refused connections and error statuses apart.
+#[tauri::command]
+pub async fn check_update_connectivity(app: AppHandle) -> Result<(), UpdateError> {
+    ensure_enabled(&app)?;
+    let endpoint = effective_endpoint(&app).ok_or(UpdateError::NoEndpoint)?;
+    let url = expand_endpoint(&app, &endpoint);
+    let host = endpoint.host_str().unwrap_or_default().to_string();
+
+    let client = reqwest::Client::builder()
+        .timeout(CONNECTIVITY_TIMEOUT)
+        .build()
+        .map_err(|e| UpdateError::Unreachable(e.to_string()))?;
+    let response = client.get(&url).send().await.map_err(|e| {
+        if e.is_timeout() {
+            UpdateError::Unreachable(format!("timed out after {}s", CONNECTIVITY_TIMEOUT.as_secs()))
+        } else if error_chain_contains(&e, "dns error") {
+            UpdateError::DnsFailed(host.clone())
+        } else if is_connection_refused(&e) {
+            UpdateError::ConnectionRefused(host.clone())
+        } else {
+            UpdateError::Unreachable(e.to_string())
+        }
+    })?;
+    if !response.status().is_success() {
+        return Err(UpdateError::HttpStatus(response.status().as_u16()));
+    }
+    info!(%host, "Update server is reachable");
+    Ok(())
+}
+
+// Fill in the variables the updater plugin substitutes in endpoint URLs
+fn expand_endpoint(app: &AppHandle, endpoint: &Url) -> String {
+    let target = match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    };
+    endpoint
+        .as_str()
+        .replace("%7B%7Bcurrent_version%7D%7D", &app.package_info().version.to_string())
+        .replace("%7B%7Btarget%7D%7D", target)
+        .replace("%7B%7Barch%7D%7D", std::env::consts::ARCH)
+}
+
+// reqwest reports DNS failures only through the messages of its sources
+fn error_chain_contains(error: &reqwest::Error, needle: &str) -> bool {
+    let mut source: Option<&dyn std::error::Error> = Some(error);
+    while let Some(e) = source {
+        if e.to_string().contains(needle) {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+fn is_connection_refused(error: &reqwest::Error) -> bool {
+    let mut source = std::error::Error::source(error);
+    while let Some(e) = source {
+        if let Some(io) = e.downcast_ref::<std::io::Error>() {
+            if io.kind() == std::io::ErrorKind::ConnectionRefused {
+                return true;
+            }
+        }
+        source = e.source();
+    }
+    false
+}
+
+// Snooze automatic update checks; defaults to a day
+#[tauri::command]
+pub fn remind_me_later(
+    app: AppHandle,
+    settings: State<Mutex<UpdaterSettings>>,
+    hours: Option<u32>,
+) -> Result<(), String> {
+    let hours = hours.unwrap_or(DEFAULT_REMIND_LATER_HOURS);
+    settings.lock_or_recover().remind_after = Some(chrono::Utc::now() + chrono::Duration::hours(hours.into()));
+    config::persist(&app)
+}
+
+// User-initiated checks wait for any check already in flight
+async fn check_update(app: &AppHandle, force: bool) -> Result<Option<Update>, UpdateError> {
+    ensure_enabled(app)?;
+    let lock = app.state::<UpdateCheckLock>();
+    let _guard = lock.0.lock().await;
+    check_update_unlocked(app, force).await
+}
+
+#[tracing::instrument(skip_all, fields(channel = %current_channel(app), force))]
+async fn check_update_unlocked(app: &AppHandle, force: bool) -> Result<Option<Update>, UpdateError> {
+    let updater = channel_updater(app, current_channel(app), force)
+        .map_err(|e| UpdateError::UpdaterUnavailable(e.to_string()))?;
+    let update = updater
+        .check()
+        .await
+        .map_err(|e| UpdateError::CheckFailed(e.to_string()))?;
+    match &update {
+        Some(update) => info!(version = %update.version, "Update available"),
+        None => info!("No update available"),
+    }
+    Ok(update)
+}
+
+// Whether an automatic (not user-initiated) check should run now
+fn auto_check_due(app: &AppHandle) -> bool {
+    let settings = app.state::<Mutex<UpdaterSettings>>();
+    let settings = settings.lock_or_recover();
+    settings.updater_enabled
+        && settings.auto_check_updates
+        && settings.remind_after.is_none_or(|after| chrono::Utc::now() >= after)
+}
+
+async fn run_auto_check(app: &AppHandle) {
+    if !auto_check_due(app) {
+        return;
+    }
+    // Skip rather than queue behind a check the user already started
+    let lock = app.state::<UpdateCheckLock>();
+    let Ok(_guard) = lock.0.try_lock() else {
+        return;
+    };
+    match check_update_unlocked(app, false).await {
+        Ok(Some(update)) if !is_skipped(app, &update) => {
+            events::emit(app, AppEvent::UpdateAvailable(UpdateInfo::from_update(&update).await));
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Automatic update check failed: {}", e),
+    }
+}
+
+pub fn start_startup_check(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(STARTUP_CHECK_DELAY).await;
+        run_auto_check(&app).await;
+    });
+}
+
+// Re-check every `update_check_interval_hours` for installs left open for days
+pub fn start_update_poller(app: &AppHandle) {
+    let (enabled, interval_hours) = {
+        let settings = app.state::<Mutex<UpdaterSettings>>();
+        let settings = settings.lock_or_recover();
+        (settings.updater_enabled, settings.update_check_interval_hours)
+    };
+    if !enabled || interval_hours == 0 {
+        return;
+    }
+
+    let handle = {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let period = Duration::from_secs(u64::from(interval_hours) * 60 * 60);
+            // The startup check covers launch, so the first tick is one period out
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                run_auto_check(&app).await;
+            }
+        })
+    };
+    *app.state::<UpdatePoller>().0.lock_or_recover() = Some(handle);
+}
+
+pub fn stop_update_poller(app: &AppHandle) {
+    if let Some(handle) = app.state::<UpdatePoller>().0.lock_or_recover().take() {
+        handle.abort();
+    }
+}
+
+// Fetch the latest update for the current channel and save it under the
+// app cache dir, ready for apply_update
+#[tauri::command]
+pub async fn download_update(app: AppHandle) -> Result<String, UpdateError> {
+    let Some(update) = check_update(&app, false).await.inspect_err(|e| report_failure(&app, e))? else {
+        return Err(UpdateError::NoUpdate);
+    };
+    ensure_disk_space(&app, &update).await.inspect_err(|e| report_failure(&app, e))?;
+
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    {
+        let cancel = app.state::<UpdateCancel>();
+        let mut cancel = cancel.0.lock_or_recover();
+        if cancel.is_some() {
+            return Err(UpdateError::DownloadInProgress);
+        }
+        *cancel = Some(cancel_tx);
+    }
+    let result = download_artifact(&app, update, cancel_rx).await;
+    app.state::<UpdateCancel>().0.lock_or_recover().take();
+    result.inspect_err(|e| report_failure(&app, e))
+}
+
+// Result of check_disk_space. `required` is None when the update's size
+// isn't known; `sufficient` is then always true.
+#[derive(Clone, Serialize)]
+pub struct DiskSpace {
+    path: String,
+    available: u64,
+    required: Option<u64>,
+    sufficient: bool,
+}
+
+// Free space on the disk updates are downloaded to, against what an update
+// of `download_size` bytes needs. The UI can pass UpdateInfo's
+// download_size to warn before the user starts the download.
+#[tauri::command]
+pub fn check_disk_space(app: AppHandle, download_size: Option<u64>) -> Result<DiskSpace, UpdateError> {
+    let dir = updates_dir(&app)
+        .map_err(|e| UpdateError::DownloadFailed(format!("failed to resolve the app cache dir: {}", e)))?;
+    let available = available_space(&dir)
+        .ok_or_else(|| UpdateError::DownloadFailed(format!("couldn't find the disk holding {}", dir.display())))?;
+    let required = download_size.map(required_space);
+    Ok(DiskSpace {
+        path: dir.display().to_string(),
+        available,
+        required,
+        sufficient: required.is_none_or(|required| available >= required),
+    })
+}
+
+// Fail before the download rather than when the artifact is saved. Skipped
+// when the server doesn't say how big the update is or the free space
+// can't be read.
+async fn ensure_disk_space(app: &AppHandle, update: &Update) -> Result<(), UpdateError> {
+    let Some(size) = artifact_size(&update.download_url).await else {
+        return Ok(());
+    };
+    let Ok(dir) = updates_dir(app) else {
+        return Ok(());
+    };
+    let Some(available) = available_space(&dir) else {
+        warn!(dir = %dir.display(), "Couldn't read the free disk space, downloading anyway");
+        return Ok(());
+    };
+    let required = required_space(size);
+    if available < required {
+        warn!(required, available, "Not enough disk space for the update");
+        return Err(UpdateError::InsufficientDiskSpace { required, available });
+    }
+    Ok(())
+}
+
+fn required_space(download_size: u64) -> u64 {
+    download_size.saturating_mul(2).saturating_add(DISK_SPACE_MARGIN_BYTES)
+}
+
+// Free bytes on the disk holding `path`, which needn't exist yet: the one
+// with the longest mount point above it
+fn available_space(path: &std::path::Path) -> Option<u64> {
+    let existing = path.ancestors().find(|dir| dir.exists())?;
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| existing.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+// Send `update-error` for an actual failure
+fn report_failure(app: &AppHandle, error: &UpdateError) {
+    let Some(stage) = error.stage() else {
+        return;
+    };
+    events::emit(app, AppEvent::UpdateError(UpdateFailure {
+        stage,
+        kind: error.kind(),
+        message: error.to_string(),
+        retryable: error.retryable(),
+    }));
+}
+
+// The plugin checks the signature as part of the download
+fn download_error(error: tauri_plugin_updater::Error) -> UpdateError {
+    use tauri_plugin_updater::Error;
+    match error {
+        Error::Minisign(_) | Error::Base64(_) | Error::SignatureUtf8(_) => {
+            UpdateError::VerificationFailed(error.to_string())
+        }
+        Error::Io(e) if e.kind() == std::io::ErrorKind::StorageFull => UpdateError::DiskFull(e.to_string()),
+        e => UpdateError::DownloadFailed(e.to_string()),
+    }
+}
+
+// Writing the artifact out; a full disk gets its own error
+fn save_error(path: &std::path::Path, e: std::io::Error) -> UpdateError {
+    if e.kind() == std::io::ErrorKind::StorageFull {
+        UpdateError::DiskFull(format!("failed to save {}: {}", path.display(), e))
+    } else {
+        UpdateError::DownloadFailed(format!("failed to save {}: {}", path.display(), e))
+    }
+}
+
+// Stop the download started by download_update or install_update
+#[tauri::command]
+pub fn cancel_update(cancel: State<UpdateCancel>) -> Result<(), UpdateError> {
+    match cancel.0.lock_or_recover().take() {
+        Some(cancel_tx) => {
+            let _ = cancel_tx.send(());
+            Ok(())
+        }
+        None => Err(UpdateError::NoDownloadInProgress),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(version = %update.version))]
+async fn download_artifact(
+    app: &AppHandle,
+    update: Update,
+    cancel_rx: oneshot::Receiver<()>,
+) -> Result<String, UpdateError> {
+    let path = artifact_path(app, &update)?;
+    let mut downloaded_bytes: u64 = 0;
+    let mut last_emit: Option<Instant> = None;
+    let mut last_percent: Option<f64> = None;
+    // (when, downloaded_bytes) at each emit, covering about RATE_WINDOW
+    let mut samples = VecDeque::from([(Instant::now(), 0)]);
+    let download = update.download(|chunk_length, total_bytes| {
+        downloaded_bytes += chunk_length as u64;
+        let finished = total_bytes.is_some_and(|total| downloaded_bytes >= total);
+        let percent = total_bytes
+            .filter(|&total| total > 0)
+            .map(|total| (downloaded_bytes as f64 / total as f64 * 100.0).min(100.0));
+        if !finished {
+            if last_emit.is_some_and(|at| at.elapsed() < PROGRESS_EMIT_INTERVAL) {
+                return;
+            }
+            // Without a total there's no percentage, so the interval alone applies
+            if let (Some(percent), Some(last)) = (percent, last_percent) {
+                if percent - last < PROGRESS_EMIT_STEP_PERCENT {
+                    return;
+                }
+            }
+        }
+        let now = Instant::now();
+        last_emit = Some(now);
+        last_percent = percent;
+        samples.push_back((now, downloaded_bytes));
+        while samples.len() > 2 && now - samples[1].0 >= RATE_WINDOW {
+            samples.pop_front();
+        }
+        let bytes_per_second = download_rate(&samples);
+        let eta_secs = match (total_bytes, bytes_per_second) {
+            (Some(total), Some(rate)) if rate > 0.0 => {
+                Some((total.saturating_sub(downloaded_bytes) as f64 / rate).ceil() as u64)
+            }
+            _ => None,
+        };
+        events::emit(app, AppEvent::UpdateProgress(UpdateProgress {
+            downloaded_bytes,
+            total_bytes,
+            percent,
+            bytes_per_second,
+            eta_secs,
+        }));
+    }, || {
+        // Called when the last byte arrives, before the artifact is saved;
+        // kept for older frontends, update-download-complete replaces it
+        events::emit(app, AppEvent::UpdateDownloaded);
+    });
+
+    // Dropping the download future aborts the request mid-stream
+    let bytes = tokio::select! {
+        result = download => result.map_err(download_error)?,
+        _ = cancel_rx => {
+            // The plugin buffers the download in memory, but don't leave a
+            // stale artifact from an earlier attempt behind either
+            let _ = std::fs::remove_file(&path);
+            info!("Update download cancelled");
+            events::emit(app, AppEvent::UpdateCancelled);
+            return Err(UpdateError::Cancelled);
+        }
+    };
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| UpdateError::DownloadFailed(format!("failed to create {}: {}", dir.display(), e)))?;
+    }
+    std::fs::write(&path, &bytes).map_err(|e| {
+        // Don't leave a partial artifact taking up what little space is left
+        let _ = std::fs::remove_file(&path);
+        save_error(&path, e)
+    })?;
+    info!(path = %path.display(), bytes = bytes.len(), "Update downloaded");
+    events::emit(app, AppEvent::UpdateDownloadComplete(UpdateDownloadComplete {
+        version: update.version.clone(),
+        bytes: bytes.len() as u64,
+    }));
+
+    let version = update.version.clone();
+    *app.state::<PendingUpdate>().0.lock_or_recover() = Some(DownloadedUpdate { update, path });
+    Ok(version)
+}
+
+// Average rate between the oldest and newest sample
+fn download_rate(samples: &VecDeque<(Instant, u64)>) -> Option<f64> {
+    let (&(first_at, first_bytes), &(last_at, last_bytes)) = (samples.front()?, samples.back()?);
+    let elapsed = last_at.duration_since(first_at).as_secs_f64();
+    if elapsed <= 0.0 {
+        return None;
+    }
+    Some(last_bytes.saturating_sub(first_bytes) as f64 / elapsed)
+}
+
+// Install the update fetched by download_update. With `relaunch` the app
+// emits `update-installed` and restarts into the new version; otherwise the
+// user is asked to restart. Waits for the backend to go idle first unless
+// `force` is set; if it doesn't, fails with `sidecarBusy` and keeps the
+// download for another try.
+#[tauri::command]
+pub async fn apply_update(app: AppHandle, relaunch: Option<bool>, force: Option<bool>) -> Result<String, UpdateError> {
+    install_pending(app.clone(), relaunch, force.unwrap_or(false)).await.inspect_err(|e| report_failure(&app, e))
+}
+
+async fn install_pending(app: AppHandle, relaunch: Option<bool>, force: bool) -> Result<String, UpdateError> {
+    ensure_enabled(&app)?;
+    let Some(version) = app.state::<PendingUpdate>().0.lock_or_recover().as_ref().map(|p| p.update.version.clone())
+    else {
+        return Err(UpdateError::NotDownloaded);
+    };
+    if !force {
+        drain_sidecar(&app, version).await?;
+    }
+    let Some(pending) = app.state::<PendingUpdate>().0.lock_or_recover().take() else {
+        return Err(UpdateError::NotDownloaded);
+    };
+
+    let bytes = std::fs::read(&pending.path)
+        .map_err(|e| UpdateError::InstallFailed(format!("failed to read {}: {}", pending.path.display(), e)))?;
+    if let Err(e) = verify_checksum(&pending.update, &bytes) {
+        warn!("{}", e);
+        let _ = std::fs::remove_file(&pending.path);
+        return Err(e);
+    }
+    let version = pending.update.version.clone();
+    let relaunch = relaunch.unwrap_or(false);
+    // On Windows install() runs the installer and exits the app without
+    // going through the exit handlers, which would leave the sidecar
+    // running. A relaunch would otherwise race the old sidecar for its port.
+    let stop_first = relaunch || cfg!(windows);
+    if stop_first {
+        crate::shutdown(&app).await;
+    }
+    // A successful install on Windows never returns, so record it up front
+    let recorded_early = cfg!(windows);
+    if recorded_early {
+        crate::update_history::record(&app, &version, current_channel(&app));
+    }
+    info!(%version, "Installing update");
+    let result = pending.update.install(bytes);
+    let _ = std::fs::remove_file(&pending.path);
+
+    match result {
+        Ok(_) => {
+            if !recorded_early {
+                crate::update_history::record(&app, &version, current_channel(&app));
+            }
+            events::emit(&app, AppEvent::UpdateInstalled(version.clone()));
+            events::emit(&app, AppEvent::UpdateInstallComplete(UpdateInstallComplete {
+                version: version.clone(),
+                relaunch,
+            }));
+            if relaunch {
+                info!(%version, "Update installed, relaunching");
+                app.restart();
+            }
+            Ok("Update installed successfully. Please restart the application.".to_string())
+        }
+        Err(e) => {
+            tracing::error!("Failed to install update: {}", e);
+            if recorded_early {
+                crate::update_history::unrecord(&app, &version);
+            }
+            if stop_first {
+                crate::resume(&app);
+            }
+            Err(UpdateError::InstallFailed(e.to_string()))
+        }
+    }
+}
+
+// Give the backend up to `drain_timeout_secs` to finish its in-flight
+// requests so installing doesn't kill it mid-write. If it's still busy,
+// emit `update-drain-timeout` and leave the choice to the user.
+async fn drain_sidecar(app: &AppHandle, version: String) -> Result<(), UpdateError> {
+    let timeout_secs = app.state::<Mutex<UpdaterSettings>>().lock_or_recover().drain_timeout_secs;
+    if timeout_secs == 0 {
+        return Ok(());
+    }
+    if crate::sidecar_http::wait_until_idle(app, Duration::from_secs(timeout_secs)).await {
+        return Ok(());
+    }
+    warn!(timeout_secs, "Sidecar is still busy, not installing the update");
+    events::emit(app, AppEvent::UpdateDrainTimeout(UpdateDrainTimeout {
+        version,
+        waited_secs: timeout_secs,
+    }));
+    Err(UpdateError::SidecarBusy)
+}
+
+#[tauri::command]
+pub async fn install_update(
+    app: AppHandle,
+    relaunch: Option<bool>,
+    force: Option<bool>,
+) -> Result<String, UpdateError> {
+    download_update(app.clone()).await?;
+    apply_update(app, relaunch, force).await
+}
+
+// Drop the downloaded update, if any, and delete every artifact under the
+// updates dir. Returns the number of bytes freed.
+#[tauri::command]
+pub fn clear_pending_update(app: AppHandle) -> Result<u64, UpdateError> {
+    if app.state::<UpdateCancel>().0.lock_or_recover().is_some() {
+        return Err(UpdateError::DownloadInProgress);
+    }
+    app.state::<PendingUpdate>().0.lock_or_recover().take();
+    let freed = remove_artifacts(&app, None);
+    info!(bytes = freed, "Cleared downloaded updates");
+    Ok(freed)
+}
+
+// The update signing key pinned in tauri.conf.json
+#[derive(Clone, Serialize)]
+pub struct UpdatePublicKey {
+    // As configured: base64 of the minisign public key file
+    key: String,
+    // The key file's comment, which names the key id
+    comment: Option<String>,
+}
+
+// Result of a successful verify_update_signature
+#[derive(Clone, Serialize)]
+pub struct SignatureVerification {
+    version: String,
+    path: String,
+    bytes: u64,
+    // Signed along with the artifact; holds the signing time and file name
+    trusted_comment: String,
+}
+
+#[tauri::command]
+pub fn get_update_public_key(app: AppHandle) -> Option<UpdatePublicKey> {
+    let key = pinned_pubkey(&app)?;
+    let comment = decode_base64_text(&key)
+        .ok()
+        .and_then(|decoded| minisign_verify::PublicKey::decode(&decoded).ok())
+        .and_then(|pubkey| pubkey.untrusted_comment().map(str::to_string));
+    Some(UpdatePublicKey { key, comment })
+}
+
+// Check the downloaded update against the pinned key again, independently
+// of the plugin. An artifact that fails is deleted and reported as a
+// non-retryable `update-error`.
+#[tauri::command]
+pub fn verify_update_signature(app: AppHandle) -> Result<SignatureVerification, UpdateError> {
+    let (version, path, signature) = {
+        let pending = app.state::<PendingUpdate>();
+        let pending = pending.0.lock_or_recover();
+        let pending = pending.as_ref().ok_or(UpdateError::NotDownloaded)?;
+        (pending.update.version.clone(), pending.path.clone(), pending.update.signature.clone())
+    };
+    let bytes = std::fs::read(&path)
+        .map_err(|e| UpdateError::VerificationFailed(format!("failed to read {}: {}", path.display(), e)))?;
+    let pubkey = pinned_pubkey(&app).ok_or_else(|| UpdateError::VerificationFailed("no key is pinned".to_string()))?;
+
+    match verify_signature(&pubkey, &signature, &bytes) {
+        Ok(trusted_comment) => {
+            info!(%version, path = %path.display(), "Update signature verified");
+            Ok(SignatureVerification {
+                version,
+                path: path.display().to_string(),
+                bytes: bytes.len() as u64,
+                trusted_comment,
+            })
+        }
+        Err(detail) => {
+            warn!(%version, "Update signature check failed: {}", detail);
+            {
+                let pending = app.state::<PendingUpdate>();
+                let mut pending = pending.0.lock_or_recover();
+                if pending.as_ref().is_some_and(|pending| pending.path == path) {
+                    pending.take();
+                }
+            }
+            let _ = std::fs::remove_file(&path);
+            let error = UpdateError::VerificationFailed(detail);
+            report_failure(&app, &error);
+            Err(error)
+        }
+    }
+}
+
+// Both the key and the signature are base64 of minisign's text format.
+// Returns the signature's trusted comment.
+fn verify_signature(pubkey: &str, signature: &str, bytes: &[u8]) -> Result<String, String> {
+    let pubkey = decode_base64_text(pubkey)
+        .and_then(|decoded| minisign_verify::PublicKey::decode(&decoded).map_err(|e| e.to_string()))
+        .map_err(|e| format!("invalid pinned key: {}", e))?;
+    let signature = decode_base64_text(signature)
+        .and_then(|decoded| minisign_verify::Signature::decode(&decoded).map_err(|e| e.to_string()))
+        .map_err(|e| format!("invalid signature: {}", e))?;
+    pubkey.verify(bytes, &signature, true).map_err(|e| e.to_string())?;
+    Ok(signature.trusted_comment().to_string())
+}
+
+fn decode_base64_text(encoded: &str) -> Result<String, String> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(encoded.trim()).map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+// Run at startup for users who keep skipping updates
+pub fn remove_stale_artifacts(app: &AppHandle) {
+    let freed = remove_artifacts(app, Some(STALE_ARTIFACT_AGE));
+    if freed > 0 {
+        info!(bytes = freed, "Removed stale update downloads");
+    }
+}
+
+// Delete the files under the updates dir, or only those last modified at
+// least `min_age` ago, returning the bytes freed. Files that can't be
+// removed are logged and skipped.
+fn remove_artifacts(app: &AppHandle, min_age: Option<Duration>) -> u64 {
+    let Ok(dir) = updates_dir(app) else {
+        return 0;
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return 0;
+    };
+    let mut freed = 0;
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        let age = metadata.modified().ok().and_then(|modified| modified.elapsed().ok());
+        if min_age.is_some_and(|min_age| age.is_none_or(|age| age < min_age)) {
+            continue;
+        }
+        match std::fs::remove_file(entry.path()) {
+            Ok(()) => freed += metadata.len(),
+            Err(e) => warn!("Failed to remove {}: {}", entry.path().display(), e),
+        }
+    }
+    freed
+}
+
+pub fn updates_dir(app: &AppHandle) -> Result<PathBuf, tauri::Error> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(UPDATES_DIR))
+}
+
+// Downloads are named after the last segment of their URL
+fn artifact_path(app: &AppHandle, update: &Update) -> Result<PathBuf, UpdateError> {
+    let file_name = update
+        .download_url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("update-{}", update.version));
+    let dir = updates_dir(app)
+        .map_err(|e| UpdateError::DownloadFailed(format!("failed to resolve the app cache dir: {}", e)))?;
+    Ok(dir.join(file_name))
+}
+
+// The manifest doesn't carry the artifact size, so ask the server for it
+async fn artifact_size(url: &Url) -> Option<u64> {
+    let client = reqwest::Client::builder()
+        .timeout(ARTIFACT_SIZE_TIMEOUT)
+        .build()
+        .ok()?;
+    let response = client.head(url.as_str()).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.content_length().filter(|&len| len > 0)
+}
+
+// Besides the signature, releases may publish a `sha256` for each artifact:
+// per platform in a static manifest, or top-level from an update server.
+// Artifacts without one are installed on the signature check alone.
+fn verify_checksum(update: &Update, bytes: &[u8]) -> Result<(), UpdateError> {
+    let Some(expected) = expected_sha256(update) else {
+        return Ok(());
+    };
+    let actual = format!("{:x}", Sha256::digest(bytes));
+    if actual.eq_ignore_ascii_case(&expected) {
+        Ok(())
+    } else {
+        Err(UpdateError::ChecksumMismatch { expected, actual })
+    }
+}
+
+fn expected_sha256(update: &Update) -> Option<String> {
+    let manifest = &update.raw_json;
+    let entry = match manifest.get("platforms").and_then(|platforms| platforms.as_object()) {
+        Some(platforms) => platforms.values().find(|platform| {
+            platform.get("url").and_then(|url| url.as_str()) == Some(update.download_url.as_str())
+        })?,
+        None => manifest,
+    };
+    entry.get("sha256").and_then(|hash| hash.as_str()).map(str::to_string)
+}
@@ -0,0 +1,127 @@
+// Main window geometry
+//
+// Saves the window's position and size to window-state.json in the app
+// config dir whenever it's moved or resized, and restores them on the next
+// launch. Coordinates are physical pixels.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, WebviewWindow, Window};
+
+const WINDOW_STATE_FILE: &str = "window-state.json";
+// Moves and resizes arrive continuously while dragging; save once it settles
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct WindowGeometry {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+// Bumped on every move/resize; a pending save only runs if it's still current
+pub struct WindowStateGeneration(pub AtomicU64);
+
+pub fn restore(window: &WebviewWindow) {
+    let Some(geometry) = load(window.app_handle()) else {
+        return;
+    };
+    let monitors = window.available_monitors().unwrap_or_default();
+    let primary = window.primary_monitor().ok().flatten();
+    let Some(geometry) = clamp_to_monitors(geometry, monitors, primary) else {
+        return;
+    };
+    let _ = window.set_size(PhysicalSize::new(geometry.width, geometry.height));
+    let _ = window.set_position(PhysicalPosition::new(geometry.x, geometry.y));
+}
+
+pub fn schedule_save(window: &Window) {
+    let generation = window.state::<WindowStateGeneration>().0.fetch_add(1, Ordering::SeqCst) + 1;
+    let window = window.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(SAVE_DEBOUNCE).await;
+        if window.state::<WindowStateGeneration>().0.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        // Minimized and maximized geometry isn't worth restoring
+        if window.is_minimized().unwrap_or(true) || window.is_maximized().unwrap_or(true) {
+            return;
+        }
+        let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+            return;
+        };
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        let geometry = WindowGeometry {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        };
+        if let Err(e) = save(window.app_handle(), geometry) {
+            tracing::warn!("Failed to save window state: {}", e);
+        }
+    });
+}
+
+// Keep the window on whichever monitor holds its center, falling back to
+// the primary monitor when the one it was saved on is gone
+fn clamp_to_monitors(
+    geometry: WindowGeometry,
+    monitors: Vec<Monitor>,
+    primary: Option<Monitor>,
+) -> Option<WindowGeometry> {
+    let center_x = geometry.x + geometry.width as i32 / 2;
+    let center_y = geometry.y + geometry.height as i32 / 2;
+    let monitor = monitors
+        .into_iter()
+        .find(|monitor| {
+            let (position, size) = (monitor.position(), monitor.size());
+            center_x >= position.x
+                && center_x < position.x + size.width as i32
+                && center_y >= position.y
+                && center_y < position.y + size.height as i32
+        })
+        .or(primary)?;
+
+    let (position, size) = (monitor.position(), monitor.size());
+    let width = geometry.width.min(size.width);
+    let height = geometry.height.min(size.height);
+    Some(WindowGeometry {
+        x: geometry.x.clamp(position.x, position.x + (size.width - width) as i32),
+        y: geometry.y.clamp(position.y, position.y + (size.height - height) as i32),
+        width,
+        height,
+    })
+}
+
+fn state_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_config_dir().ok().map(|dir| dir.join(WINDOW_STATE_FILE))
+}
+
+fn load(app: &AppHandle) -> Option<WindowGeometry> {
+    let path = state_path(app)?;
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| tracing::warn!("Ignoring invalid {}: {}", path.display(), e))
+            .ok(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            tracing::warn!("Failed to read {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn save(app: &AppHandle, geometry: WindowGeometry) -> Result<(), String> {
+    let path = state_path(app).ok_or("Failed to resolve the app config dir")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let contents = serde_json::to_string_pretty(&geometry).map_err(|e| e.to_string())?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
@@ -1,6 +1,38 @@
 import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import { ConfirmModal } from "./ConfirmModal";
+
+interface UpdateProgress {
+  downloaded_bytes: number;
+  total_bytes: number | null;
+  percent: number | null;
+  bytes_per_second: number | null;
+  eta_secs: number | null;
+}
+
+// e.g. "3.2 MB/s — about 12s left"
+function describeSpeed({ bytes_per_second, eta_secs }: UpdateProgress): string | null {
+  if (bytes_per_second === null) {
+    return null;
+  }
+  const speed = `${(bytes_per_second / 1_000_000).toFixed(1)} MB/s`;
+  if (eta_secs === null) {
+    return speed;
+  }
+  const left = eta_secs >= 60 ? `${Math.ceil(eta_secs / 60)}m` : `${eta_secs}s`;
+  return `${speed} — about ${left} left`;
+}
+
+// Mirrors UpdateError in src-tauri/src/updater.rs
+interface UpdateError {
+  kind: string;
+  message: string;
+}
+
+function isUpdateError(err: unknown): err is UpdateError {
+  return typeof err === "object" && err !== null && "kind" in err && "message" in err;
+}
 
 interface UpdateNotificationProps {
   onUpdateAvailable?: (version: string) => void;
@@ -11,7 +43,11 @@ export function UpdateNotification({ onUpdateAvailable }: UpdateNotificationProp
   const [updateMessage, setUpdateMessage] = useState("");
   const [isInstalling, setIsInstalling] = useState(false);
   const [installProgress, setInstallProgress] = useState(0);
+  const [downloadSpeed, setDownloadSpeed] = useState<string | null>(null);
   const [error, setError] = useState<string | null>(null);
+  const [installedVersion, setInstalledVersion] = useState<string | null>(null);
+  // The backend didn't finish its work in time; ask before installing anyway
+  const [confirmForce, setConfirmForce] = useState(false);
 
   useEffect(() => {
     // Check for updates on mount (only in production)
@@ -20,17 +56,28 @@ export function UpdateNotification({ onUpdateAvailable }: UpdateNotificationProp
     }
 
     // Listen for update progress events
-    const unlistenProgress = listen<number>("update-progress", (event) => {
-      setInstallProgress(Math.round(event.payload));
+    const unlistenProgress = listen<UpdateProgress>("update-progress", (event) => {
+      if (event.payload.percent !== null) {
+        setInstallProgress(Math.round(event.payload.percent));
+      }
+      setDownloadSpeed(describeSpeed(event.payload));
     });
 
-    const unlistenDownloaded = listen("update-downloaded", () => {
+    const unlistenDownloaded = listen("update-download-complete", () => {
       setInstallProgress(100);
     });
 
+    const unlistenInstalled = listen<{ version: string; relaunch: boolean }>("update-install-complete", (event) => {
+      setInstalledVersion(event.payload.version);
+      if (!event.payload.relaunch) {
+        setUpdateMessage(`Restart the app to apply v${event.payload.version}.`);
+      }
+    });
+
     return () => {
       unlistenProgress.then((fn) => fn());
       unlistenDownloaded.then((fn) => fn());
+      unlistenInstalled.then((fn) => fn());
     };
   }, []);
 
@@ -53,17 +100,35 @@ export function UpdateNotification({ onUpdateAvailable }: UpdateNotificationProp
     setIsInstalling(true);
     setError(null);
     setInstallProgress(0);
+    setDownloadSpeed(null);
 
     try {
-      const result = await invoke<string>("install_update");
-      setUpdateMessage(result);
+      await invoke<string>("install_update");
 
       // Show success message for a moment before restarting
       setTimeout(() => {
         // The app will be restarted automatically by the updater
       }, 2000);
     } catch (err) {
-      setError(err instanceof Error ? err.message : String(err));
+      if (isUpdateError(err) && err.kind === "sidecarBusy") {
+        setConfirmForce(true);
+      } else {
+        setError(isUpdateError(err) ? err.message : String(err));
+      }
+      setIsInstalling(false);
+    }
+  };
+
+  // The update is already downloaded at this point
+  const forceInstall = async () => {
+    setConfirmForce(false);
+    setIsInstalling(true);
+    setError(null);
+
+    try {
+      await invoke<string>("apply_update", { force: true });
+    } catch (err) {
+      setError(isUpdateError(err) ? err.message : String(err));
       setIsInstalling(false);
     }
   };
@@ -78,65 +143,83 @@ export function UpdateNotification({ onUpdateAvailable }: UpdateNotificationProp
   }
 
   return (
-    <div className="fixed top-4 left-1/2 transform -translate-x-1/2 z-50 max-w-md w-full mx-4">
-      <div className="bg-primary/10 border-2 border-primary rounded-xl shadow-lg hand-drawn-card p-4">
-        <div className="flex items-start justify-between gap-3 mb-3">
-          <div className="flex-1">
-            <h3 className="font-semibold text-primary mb-1">Update Available</h3>
-            <p className="text-sm text-gray-700">{updateMessage}</p>
+    <>
+      <div className="fixed top-4 left-1/2 transform -translate-x-1/2 z-50 max-w-md w-full mx-4">
+        <div className="bg-primary/10 border-2 border-primary rounded-xl shadow-lg hand-drawn-card p-4">
+          <div className="flex items-start justify-between gap-3 mb-3">
+            <div className="flex-1">
+              <h3 className="font-semibold text-primary mb-1">Update Available</h3>
+              <p className="text-sm text-gray-700">{updateMessage}</p>
+            </div>
+            {!isInstalling && (
+              <button
+                type="button"
+                onClick={dismissUpdate}
+                className="text-lg opacity-70 hover:opacity-100 transition-opacity"
+                aria-label="Dismiss update notification"
+              >
+                ×
+              </button>
+            )}
           </div>
-          {!isInstalling && (
-            <button
-              type="button"
-              onClick={dismissUpdate}
-              className="text-lg opacity-70 hover:opacity-100 transition-opacity"
-              aria-label="Dismiss update notification"
-            >
-              ×
-            </button>
+
+          {error && (
+            <div className="mb-3 p-2 bg-red-50 border border-red-200 rounded text-sm text-red-700">
+              {error}
+            </div>
           )}
-        </div>
 
-        {error && (
-          <div className="mb-3 p-2 bg-red-50 border border-red-200 rounded text-sm text-red-700">
-            {error}
-          </div>
-        )}
-
-        {isInstalling && (
-          <div className="mb-3">
-            <div className="w-full bg-gray-200 rounded-full h-2 overflow-hidden">
-              <div
-                className="bg-primary h-full transition-all duration-300 ease-out"
-                style={{ width: `${installProgress}%` }}
-              />
+          {isInstalling && (
+            <div className="mb-3">
+              <div className="w-full bg-gray-200 rounded-full h-2 overflow-hidden">
+                <div
+                  className="bg-primary h-full transition-all duration-300 ease-out"
+                  style={{ width: `${installProgress}%` }}
+                />
+              </div>
+              <p className="text-xs text-gray-600 mt-1 text-center">
+                {installedVersion
+                  ? `Installed v${installedVersion}`
+                  : installProgress < 100
+                    ? `Downloading... ${installProgress}%${downloadSpeed ? ` (${downloadSpeed})` : ""}`
+                    : "Installing..."}
+              </p>
             </div>
-            <p className="text-xs text-gray-600 mt-1 text-center">
-              {installProgress < 100 ? `Downloading... ${installProgress}%` : "Installing..."}
-            </p>
-          </div>
-        )}
-
-        <div className="flex gap-2">
-          <button
-            type="button"
-            onClick={installUpdate}
-            disabled={isInstalling}
-            className="flex-1 px-4 py-2 bg-primary text-white rounded-lg font-semibold hover:bg-primary/90 disabled:opacity-50 disabled:cursor-not-allowed transition-colors"
-          >
-            {isInstalling ? "Installing..." : "Install Update"}
-          </button>
-          {!isInstalling && (
+          )}
+
+          <div className="flex gap-2">
             <button
               type="button"
-              onClick={dismissUpdate}
-              className="px-4 py-2 border-2 border-gray-300 text-gray-700 rounded-lg font-semibold hover:bg-gray-50 transition-colors"
+              onClick={installUpdate}
+              disabled={isInstalling}
+              className="flex-1 px-4 py-2 bg-primary text-white rounded-lg font-semibold hover:bg-primary/90 disabled:opacity-50 disabled:cursor-not-allowed transition-colors"
             >
-              Later
+              {isInstalling ? "Installing..." : "Install Update"}
             </button>
-          )}
+            {!isInstalling && (
+              <button
+                type="button"
+                onClick={dismissUpdate}
+                className="px-4 py-2 border-2 border-gray-300 text-gray-700 rounded-lg font-semibold hover:bg-gray-50 transition-colors"
+              >
+                Later
+              </button>
+            )}
+          </div>
         </div>
       </div>
-    </div>
+
+      {confirmForce && (
+        <ConfirmModal
+          title="Install Anyway?"
+          message="The app is still finishing some work. Installing now may lose changes that haven't been saved yet."
+          confirmLabel="Install Now"
+          cancelLabel="Wait"
+          variant="warning"
+          onConfirm={forceInstall}
+          onCancel={() => setConfirmForce(false)}
+        />
+      )}
+    </>
   );
 }
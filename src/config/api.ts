@@ -4,28 +4,67 @@
  */
 
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 // Default backend URL (used in dev mode or as fallback)
 const DEFAULT_API_URL = import.meta.env.VITE_API_BASE_URL || "http://127.0.0.1:27888";
 
 let API_BASE_URL = DEFAULT_API_URL;
 
+// How long startup waits for the sidecar before falling back to the default
+const SIDECAR_WAIT_MS = 30_000;
+
+// Per-launch token the sidecar requires in the Authorization header
+let AUTH_TOKEN: string | null = null;
+
+// Payload of `sidecar-port-changed`, sent when a restart brings a sidecar
+// up on a different port
+interface SidecarPortChanged {
+  name: string;
+  old_port: number;
+  port: number;
+}
+
+let portListenerStarted = false;
+
 /**
  * Initialize the API configuration by getting the sidecar port from Tauri
  * This should be called on app startup
  */
 export async function initializeApiConfig(): Promise<void> {
+  // Every request builds its URL from API_BASE_URL, so pointing it at the
+  // new port is enough to move the client over
+  if (!portListenerStarted) {
+    portListenerStarted = true;
+    void listen<SidecarPortChanged>("sidecar-port-changed", (event) => {
+      if (event.payload.name !== "backend") {
+        return;
+      }
+      API_BASE_URL = `http://127.0.0.1:${event.payload.port}`;
+      console.log(`Sidecar moved from port ${event.payload.old_port} to ${event.payload.port}`);
+    });
+  }
+
   try {
-    const port = await invoke<number | null>("get_sidecar_port");
-    if (port) {
-      API_BASE_URL = `http://127.0.0.1:${port}`;
-      console.log(`API configured to use sidecar port: ${port}`);
-    } else {
-      console.warn(`Sidecar port not available yet, using default: ${DEFAULT_API_URL}`);
-    }
+    const port = await invoke<number>("wait_for_sidecar", { timeoutMs: SIDECAR_WAIT_MS });
+    API_BASE_URL = `http://127.0.0.1:${port}`;
+    console.log(`API configured to use sidecar port: ${port}`);
   } catch (error) {
-    console.warn(`Failed to get sidecar port, using default: ${DEFAULT_API_URL}`, error);
+    console.warn(`Sidecar port not available, using default: ${DEFAULT_API_URL}`, error);
   }
+
+  try {
+    AUTH_TOKEN = await invoke<string>("get_sidecar_token");
+  } catch (error) {
+    console.warn("Failed to get sidecar auth token", error);
+  }
+}
+
+/**
+ * Headers to attach to every backend request
+ */
+export function authHeaders(): Record<string, string> {
+  return AUTH_TOKEN ? { Authorization: `Bearer ${AUTH_TOKEN}` } : {};
 }
 
 /**
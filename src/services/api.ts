@@ -3,7 +3,7 @@
  * Handles all HTTP communication with the Retention backend server
  */
 
-import { config, deckUrl, cardAttemptsUrl, deckBulkUrl } from "../config/api";
+import { authHeaders, config, deckUrl, cardAttemptsUrl, deckBulkUrl } from "../config/api";
 import type { GradingMode } from "../types/deck";
 
 // Types (matching the backend models)
@@ -114,6 +114,7 @@ class ApiClient {
         ...options,
         headers: {
           "Content-Type": "application/json",
+          ...authHeaders(),
           ...options?.headers,
         },
         signal: controller.signal,